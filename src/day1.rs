@@ -1,82 +1,634 @@
 const DAY: u8 = 1;
 
 pub fn main() {
-    part1();
-    part2();
-}
-
-use std::collections::HashMap;
-use std::u128;
-
-fn part1() {
-    let mut our_sum: u128 = 0;
-
-    for line in crate::utils::lines(DAY) {
-        let numbers: Vec<u32> = line.chars().filter_map(|a| a.to_digit(10)).collect();
-        our_sum += (numbers.first().unwrap() * 10) as u128 + (*numbers.last().unwrap() as u128);
-    }
-
-    done!(DAY, 1, our_sum);
-}
-
-fn part2() {
-    let mut our_sum: u128 = 0;
-    let digits: HashMap<&str, u8> = HashMap::from([
-        ("one", 1),
-        ("1", 1),
-        ("two", 2),
-        ("2", 2),
-        ("three", 3),
-        ("3", 3),
-        ("four", 4),
-        ("4", 4),
-        ("five", 5),
-        ("5", 5),
-        ("six", 6),
-        ("6", 6),
-        ("seven", 7),
-        ("7", 7),
-        ("eight", 8),
-        ("8", 8),
-        ("nine", 9),
-        ("9", 9),
-    ]);
-
-    let mut first_digit_idx: usize;
-    let mut first_digit: &u8;
-    let mut last_digit_idx: usize;
-    let mut last_digit: &u8;
-
-    for line in crate::utils::lines(DAY){
-        first_digit_idx = usize::MAX;
-        first_digit = &0;
-        last_digit_idx = 0;
-        last_digit = &0;
-
-        for digit in digits.keys() {
-            if let Some(idx) = line.find(digit) {
-                if idx < first_digit_idx {
-                    first_digit_idx = idx;
-                    first_digit = digits.get(digit).unwrap();
+    // `cargo run -- 1 --lexicon fr` or `--lexicon path/to/words.txt`
+    let lexicon = match crate::utils::arg_value("--lexicon") {
+        None => Lexicon::english(),
+        Some(name) => Lexicon::builtin(&name).unwrap_or_else(|| {
+            Lexicon::from_file(Path::new(&name))
+                .unwrap_or_else(|e| panic!("Can't load lexicon {}: {}", name, e))
+        }),
+    };
+
+    // What to do about lines without any digits: `--missing-digits fail|skip|zero`
+    let policy = crate::utils::arg_value("--missing-digits")
+        .map(|p| {
+            p.parse::<MissingDigits>()
+                .unwrap_or_else(|e| panic!("{}", e))
+        })
+        .unwrap_or(MissingDigits::Fail);
+
+    if crate::utils::has_flag("--explain") {
+        explain(&lexicon, crate::utils::has_flag("--only-disagreements"))
+            .expect("Couldn't write our explanation");
+    }
+
+    part1(policy);
+    part2(&lexicon, policy);
+}
+
+use crate::utils::ansi;
+use std::{
+    collections::VecDeque,
+    error::Error,
+    fmt, fs,
+    io::{self, BufRead, Write},
+    path::Path,
+    str::FromStr,
+};
+
+/*
+  Every word (or digit) that can stand in for a number in a calibration line.
+
+  Digits 1-9 are always in there; the words are what changes from language to
+  language.
+*/
+#[derive(Debug, Clone)]
+pub struct Lexicon {
+    words: Vec<(String, u8)>,
+}
+
+#[derive(Debug)]
+pub enum LexiconError {
+    Io(io::Error),
+    BadLine { line_no: usize, line: String },
+}
+
+impl fmt::Display for LexiconError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LexiconError::Io(e) => write!(f, "{}", e),
+            LexiconError::BadLine { line_no, line } => {
+                write!(
+                    f,
+                    "line {} isn't word=value with a value of 1-9: {:?}",
+                    line_no, line
+                )
+            }
+        }
+    }
+}
+
+impl Lexicon {
+    pub fn new<S: Into<String>>(words: impl IntoIterator<Item = (S, u8)>) -> Self {
+        let mut lexicon = Self::digits();
+        lexicon
+            .words
+            .extend(words.into_iter().map(|(w, v)| (w.into(), v)));
+        lexicon
+    }
+
+    /// Just 1-9, no words at all.
+    pub fn digits() -> Self {
+        Self {
+            words: (1..=9).map(|d| (d.to_string(), d)).collect(),
+        }
+    }
+
+    /// 0-9, exactly what `char::to_digit(10)` finds. Part 1 counts 0 as a digit, even
+    /// though no word lexicon does.
+    pub fn all_digits() -> Self {
+        Self {
+            words: (0..=9).map(|d| (d.to_string(), d)).collect(),
+        }
+    }
+
+    pub fn english() -> Self {
+        Self::new([
+            ("one", 1),
+            ("two", 2),
+            ("three", 3),
+            ("four", 4),
+            ("five", 5),
+            ("six", 6),
+            ("seven", 7),
+            ("eight", 8),
+            ("nine", 9),
+        ])
+    }
+
+    pub fn french() -> Self {
+        Self::new([
+            ("un", 1),
+            ("deux", 2),
+            ("trois", 3),
+            ("quatre", 4),
+            ("cinq", 5),
+            ("six", 6),
+            ("sept", 7),
+            ("huit", 8),
+            ("neuf", 9),
+        ])
+    }
+
+    pub fn german() -> Self {
+        Self::new([
+            ("eins", 1),
+            ("zwei", 2),
+            ("drei", 3),
+            ("vier", 4),
+            ("fünf", 5),
+            ("sechs", 6),
+            ("sieben", 7),
+            ("acht", 8),
+            ("neun", 9),
+        ])
+    }
+
+    pub fn spanish() -> Self {
+        Self::new([
+            ("uno", 1),
+            ("dos", 2),
+            ("tres", 3),
+            ("cuatro", 4),
+            ("cinco", 5),
+            ("seis", 6),
+            ("siete", 7),
+            ("ocho", 8),
+            ("nueve", 9),
+        ])
+    }
+
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "en" | "english" => Some(Self::english()),
+            "fr" | "french" => Some(Self::french()),
+            "de" | "german" => Some(Self::german()),
+            "es" | "spanish" => Some(Self::spanish()),
+            _ => None,
+        }
+    }
+
+    /// One `word=value` per line, values 1-9. Blank lines and lines starting with `#` are skipped.
+    pub fn parse(contents: &str) -> Result<Self, LexiconError> {
+        let mut words: Vec<(String, u8)> = Vec::new();
+
+        for (idx, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let bad_line = || LexiconError::BadLine {
+                line_no: idx + 1,
+                line: line.to_string(),
+            };
+
+            let (word, value) = line.split_once('=').ok_or_else(bad_line)?;
+            let word = word.trim();
+            let value = value.trim().parse::<u8>().map_err(|_| bad_line())?;
+            // Calibration values are first*10 + last, so anything but 1-9 makes a mess of them
+            if word.is_empty() || !(1..=9).contains(&value) {
+                return Err(bad_line());
+            }
+
+            words.push((word.to_string(), value));
+        }
+
+        Ok(Self::new(words))
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, LexiconError> {
+        Self::parse(&fs::read_to_string(path).map_err(LexiconError::Io)?)
+    }
+
+    pub fn words(&self) -> impl Iterator<Item = (&str, u8)> {
+        self.words.iter().map(|(w, v)| (w.as_str(), *v))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match {
+    /// Byte offset into the line
+    pub start: usize,
+    pub len: usize,
+    pub value: u8,
+}
+
+/*
+  An Aho-Corasick automaton over bytes, flattened into a full DFA so that each
+  input byte costs exactly one table lookup no matter how big the vocabulary is.
+*/
+#[derive(Debug)]
+struct Automaton {
+    next: Vec<[u32; 256]>,
+    // The longest word that ends when we land in this state, as (length, value)
+    out: Vec<Option<(usize, u8)>>,
+}
+
+impl Automaton {
+    fn new<'a>(words: impl Iterator<Item = (&'a [u8], u8)>) -> Self {
+        const NONE: u32 = u32::MAX;
+        let mut next: Vec<[u32; 256]> = vec![[NONE; 256]];
+        let mut out: Vec<Option<(usize, u8)>> = vec![None];
+
+        // Plain trie first
+        for (word, value) in words {
+            let mut state = 0;
+            for b in word {
+                if next[state][*b as usize] == NONE {
+                    next[state][*b as usize] = next.len() as u32;
+                    next.push([NONE; 256]);
+                    out.push(None);
                 }
+                state = next[state][*b as usize] as usize;
+            }
+            out[state] = Some((word.len(), value));
+        }
+
+        // Then fill in failure transitions breadth first, so shallower states are always done first
+        let mut fail: Vec<usize> = vec![0; next.len()];
+        let mut queue: VecDeque<usize> = VecDeque::new();
+
+        for slot in next[0].iter_mut() {
+            match *slot {
+                NONE => *slot = 0,
+                child => queue.push_back(child as usize),
+            }
+        }
+
+        while let Some(state) = queue.pop_front() {
+            // A state's own word is always longer than anything it inherits from its failure state
+            out[state] = out[state].or(out[fail[state]]);
 
-                // We haven't found a last digit yet, there may only be this one digit!
-                if last_digit == &0 {
-                    last_digit_idx = idx;
-                    last_digit = digits.get(digit).unwrap();
+            let fail_row = next[fail[state]];
+            for (slot, fail_next) in next[state].iter_mut().zip(fail_row) {
+                match *slot {
+                    NONE => *slot = fail_next,
+                    child => {
+                        fail[child as usize] = fail_next as usize;
+                        queue.push_back(child as usize);
+                    }
                 }
             }
+        }
+
+        Self { next, out }
+    }
+}
+
+/*
+  Finds the first and last lexicon words in a line in a single pass each way.
+
+  "First" is whichever word starts earliest and "last" whichever starts latest,
+  so overlapping words like "eightwo" resolve to 8 at the front and 2 at the back.
+  If two words start at the same spot the longer one wins.
+*/
+#[derive(Debug)]
+pub struct Scanner {
+    forward: Automaton,
+    // Built from the words spelled backwards, for scanning from the end of the line
+    backward: Automaton,
+    max_len: usize,
+}
 
-            if let Some(idx) = line.rfind(digit) {
-                if idx > last_digit_idx {
-                    last_digit_idx = idx;
-                    last_digit = digits.get(digit).unwrap();
+impl Scanner {
+    pub fn new(lexicon: &Lexicon) -> Self {
+        let words: Vec<(&[u8], u8)> = lexicon
+            .words()
+            .filter(|(w, _)| !w.is_empty())
+            .map(|(w, v)| (w.as_bytes(), v))
+            .collect();
+        let reversed: Vec<(Vec<u8>, u8)> = words
+            .iter()
+            .map(|(w, v)| (w.iter().rev().copied().collect(), *v))
+            .collect();
+
+        Self {
+            forward: Automaton::new(words.iter().copied()),
+            backward: Automaton::new(reversed.iter().map(|(w, v)| (w.as_slice(), *v))),
+            max_len: words.iter().map(|(w, _)| w.len()).max().unwrap_or(0),
+        }
+    }
+
+    pub fn first(&self, line: &[u8]) -> Option<Match> {
+        let mut state = 0;
+        let mut best: Option<Match> = None;
+
+        for (idx, b) in line.iter().enumerate() {
+            state = self.forward.next[state][*b as usize] as usize;
+
+            if let Some((len, value)) = self.forward.out[state] {
+                let start = idx + 1 - len;
+                if best.is_none_or(|m| start < m.start || (start == m.start && len > m.len)) {
+                    best = Some(Match { start, len, value });
                 }
             }
+
+            // Matches come out in order of where they *end*, so keep going until
+            // nothing still to come could possibly start before (or with) our best one
+            if best.is_some_and(|m| idx + 1 >= m.start + self.max_len) {
+                break;
+            }
+        }
+
+        best
+    }
+
+    pub fn last(&self, line: &[u8]) -> Option<Match> {
+        let mut state = 0;
+
+        // Backwards, matches come out in order of where they start, latest first. So the first one wins.
+        for (idx, b) in line.iter().enumerate().rev() {
+            state = self.backward.next[state][*b as usize] as usize;
+
+            if let Some((len, value)) = self.backward.out[state] {
+                return Some(Match {
+                    start: idx,
+                    len,
+                    value,
+                });
+            }
+        }
+
+        None
+    }
+
+    /// The two-digit calibration value for a line, if it has anything we recognize in it.
+    pub fn calibration_value(&self, line: &[u8]) -> Option<u32> {
+        let first = self.first(line)?;
+        let last = self.last(line)?;
+
+        Some(first.value as u32 * 10 + last.value as u32)
+    }
+}
+
+/// Calls `f` with every (1-based) line number and line of our input, minus the line ending,
+/// stopping at the first error. Works on raw bytes, reusing one buffer, so even huge files
+/// stream through without piling up.
+fn for_each_line<E>(mut f: impl FnMut(usize, &[u8]) -> Result<(), E>) -> Result<(), E> {
+    let mut reader = crate::utils::get_reader_for_day(DAY);
+    let mut buf: Vec<u8> = Vec::new();
+    let mut line_no = 0;
+
+    while reader
+        .read_until(b'\n', &mut buf)
+        .expect("Can't read our input?!")
+        > 0
+    {
+        line_no += 1;
+        let line = buf.strip_suffix(b"\n").unwrap_or(&buf);
+        f(line_no, line.strip_suffix(b"\r").unwrap_or(line))?;
+        buf.clear();
+    }
+
+    Ok(())
+}
+
+/// The line with the first match in green and the last in yellow. Any overlap
+/// between them (or when both are the same match) is magenta.
+fn highlight(line: &[u8], first: Option<Match>, last: Option<Match>) -> String {
+    let within =
+        |m: Option<Match>, idx: usize| m.is_some_and(|m| idx >= m.start && idx < m.start + m.len);
+    let colour_at = |idx: usize| match (within(first, idx), within(last, idx)) {
+        (true, true) => Some(ansi::MAGENTA),
+        (true, false) => Some(ansi::GREEN),
+        (false, true) => Some(ansi::YELLOW),
+        (false, false) => None,
+    };
+
+    // Matches always start and end on character boundaries, so cutting the line wherever the colour changes is safe
+    let mut out = String::new();
+    let mut seg_start = 0;
+    for idx in 1..=line.len() {
+        if idx < line.len() && colour_at(idx) == colour_at(seg_start) {
+            continue;
+        }
+
+        let segment = String::from_utf8_lossy(&line[seg_start..idx]);
+        match colour_at(seg_start) {
+            Some(colour) => out.push_str(&ansi::paint(&segment, colour)),
+            None => out.push_str(&segment),
+        }
+        seg_start = idx;
+    }
+    out
+}
+
+fn describe(line: &[u8], first: Option<Match>, last: Option<Match>) -> String {
+    let token = |m: Match| String::from_utf8_lossy(&line[m.start..m.start + m.len]).into_owned();
+
+    match (first, last) {
+        (Some(f), Some(l)) => format!(
+            "{:?} @ {} .. {:?} @ {} => {}",
+            token(f),
+            f.start,
+            token(l),
+            l.start,
+            f.value as u32 * 10 + l.value as u32
+        ),
+        _ => "nothing found".to_string(),
+    }
+}
+
+/*
+  Shows how every line was read: the first and last tokens each part picked,
+  where they start (in bytes) and the value that came out. The line itself is
+  shown with part 2's picks highlighted.
+*/
+fn explain(lexicon: &Lexicon, only_disagreements: bool) -> io::Result<()> {
+    let digits = part1_scanner();
+    let words = Scanner::new(lexicon);
+    let mut out = io::stdout().lock();
+
+    for_each_line(|line_no, line| {
+        if only_disagreements && digits.calibration_value(line) == words.calibration_value(line) {
+            return Ok(());
+        }
+        write!(out, "{}", explain_line(&digits, &words, line_no, line))
+    })
+}
+
+fn explain_line(digits: &Scanner, words: &Scanner, line_no: usize, line: &[u8]) -> String {
+    let (first_1, last_1) = (digits.first(line), digits.last(line));
+    let (first_2, last_2) = (words.first(line), words.last(line));
+
+    format!(
+        "{:>5} | {}\n      | part 1: {}\n      | part 2: {}\n",
+        line_no,
+        highlight(line, first_2, last_2),
+        describe(line, first_1, last_1),
+        describe(line, first_2, last_2)
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MissingDigits {
+    Fail,
+    Skip,
+    Zero,
+}
+
+impl FromStr for MissingDigits {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fail" => Ok(MissingDigits::Fail),
+            "skip" => Ok(MissingDigits::Skip),
+            "zero" => Ok(MissingDigits::Zero),
+            _ => Err(format!(
+                "Unknown missing digits policy {:?}, try fail, skip or zero",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct NoDigitsError {
+    pub line_no: usize,
+    pub line: String,
+}
+
+impl fmt::Display for NoDigitsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {} has no digits: {:?}", self.line_no, self.line)
+    }
+}
+
+impl Error for NoDigitsError {}
+
+#[derive(Debug, Default)]
+pub struct Calibration {
+    pub sum: u128,
+    /// Line numbers that didn't have any digits, and so were skipped or counted as zero.
+    pub missing: Vec<usize>,
+}
+
+fn calibrate(scanner: &Scanner, policy: MissingDigits) -> Result<Calibration, NoDigitsError> {
+    let mut calibration = Calibration::default();
+
+    for_each_line(|line_no, line| {
+        match scanner.calibration_value(line) {
+            Some(value) => calibration.sum += value as u128,
+            None if policy == MissingDigits::Fail => {
+                return Err(NoDigitsError {
+                    line_no,
+                    line: String::from_utf8_lossy(line).into_owned(),
+                })
+            }
+            // Skipping and counting as zero come to the same thing for the sum
+            None => calibration.missing.push(line_no),
+        }
+        Ok(())
+    })?;
+
+    Ok(calibration)
+}
+
+fn report(part: u8, policy: MissingDigits, result: Result<Calibration, NoDigitsError>) {
+    match result {
+        Ok(calibration) => {
+            done!(DAY, part, calibration.sum);
+
+            if !calibration.missing.is_empty() {
+                println!(
+                    "        {} {} line(s) without digits: {:?}",
+                    if policy == MissingDigits::Skip {
+                        "skipped"
+                    } else {
+                        "zeroed"
+                    },
+                    calibration.missing.len(),
+                    calibration.missing
+                );
+            }
         }
+        Err(e) => eprintln!("Day {:>2}, part {} failed: {}", DAY, part, e),
+    }
+}
+
+/// What part 1 reads lines with, --explain included, so the two can't disagree.
+fn part1_scanner() -> Scanner {
+    Scanner::new(&Lexicon::all_digits())
+}
+
+fn part1(policy: MissingDigits) {
+    report(1, policy, calibrate(&part1_scanner(), policy));
+}
+
+fn part2(lexicon: &Lexicon, policy: MissingDigits) {
+    report(2, policy, calibrate(&Scanner::new(lexicon), policy));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn first_last(scanner: &Scanner, line: &str) -> (Option<Match>, Option<Match>) {
+        (
+            scanner.first(line.as_bytes()),
+            scanner.last(line.as_bytes()),
+        )
+    }
 
-        our_sum += ((first_digit * 10) + *last_digit) as u128;
+    #[test]
+    fn overlapping_words() {
+        let scanner = Scanner::new(&Lexicon::english());
+        assert_eq!(scanner.calibration_value(b"eightwo"), Some(82));
+        assert_eq!(scanner.calibration_value(b"twone"), Some(21));
+        assert_eq!(scanner.calibration_value(b"xtwonex3eightwox"), Some(22));
     }
 
-    done!(DAY, 2, our_sum);
+    #[test]
+    fn longer_word_wins_at_the_same_start() {
+        let scanner = Scanner::new(&Lexicon::new([("sev", 1), ("seven", 7)]));
+        assert_eq!(
+            scanner.first(b"xsevenx"),
+            Some(Match {
+                start: 1,
+                len: 5,
+                value: 7
+            })
+        );
+        // An earlier short word still beats a later long one
+        assert_eq!(scanner.first(b"sevseven").map(|m| m.value), Some(1));
+        assert_eq!(scanner.last(b"sevseven").map(|m| m.value), Some(7));
+    }
+
+    #[test]
+    fn multi_byte_words() {
+        let scanner = Scanner::new(&Lexicon::german());
+        let (first, last) = first_last(&scanner, "xfünfeinsx");
+        assert_eq!(
+            first,
+            Some(Match {
+                start: 1,
+                len: "fünf".len(),
+                value: 5
+            })
+        );
+        assert_eq!(last.map(|m| (m.start, m.value)), Some((6, 1)));
+    }
+
+    #[test]
+    fn one_match_is_both_ends() {
+        let scanner = Scanner::new(&Lexicon::english());
+        let (first, last) = first_last(&scanner, "abc7def");
+        assert_eq!(first, last);
+        assert_eq!(scanner.calibration_value(b"abc7def"), Some(77));
+    }
+
+    #[test]
+    fn explain_agrees_with_part_1_on_zeros() {
+        let digits = part1_scanner();
+        assert_eq!(digits.calibration_value(b"a0b7"), Some(7));
+
+        let explained = explain_line(&digits, &Scanner::new(&Lexicon::english()), 1, b"a0b7");
+        assert!(
+            explained.contains("part 1: \"0\" @ 1 .. \"7\" @ 3 => 7\n"),
+            "{}",
+            explained
+        );
+        assert!(explained.contains("part 2: \"7\" @ 3 .. \"7\" @ 3 => 77\n"));
+    }
+
+    #[test]
+    fn no_match() {
+        let scanner = Scanner::new(&Lexicon::english());
+        assert_eq!(first_last(&scanner, "abcdef"), (None, None));
+        assert_eq!(scanner.calibration_value(b"abcdef"), None);
+        assert_eq!(scanner.calibration_value(b""), None);
+    }
 }
@@ -5,8 +5,7 @@ pub fn main() {
     part2();
 }
 
-use petgraph::algo::simple_paths::all_simple_paths;
-use petgraph::graph::{NodeIndex, UnGraph};
+use crate::utils::search::bfs;
 use std::cmp::max;
 use std::collections::HashMap;
 
@@ -54,13 +53,13 @@ impl Node {
     }
 }
 
-fn build_connection_table() -> (Node, HashMap<Node, Vec<Node>>) {
+fn build_connection_table(lines: impl Iterator<Item = String>) -> (Node, HashMap<Node, Vec<Node>>) {
     let mut start: Node = Node::at(usize::MAX, usize::MAX);
     let mut curr: Node;
     let mut conn_by_node: HashMap<Node, Vec<Node>> = HashMap::new();
     let mut connections: Vec<Node>;
 
-    for (y, line) in crate::utils::lines(DAY).enumerate() {
+    for (y, line) in lines.enumerate() {
         for (x, tile) in line.chars().enumerate() {
             match tile {
                 '.' => {
@@ -75,15 +74,7 @@ fn build_connection_table() -> (Node, HashMap<Node, Vec<Node>>) {
 
             curr = Node::at(x, y);
 
-            // NOTE: Using flatten() means having to borrow a bunch and I couldn't figure out how to make that work
-            connections = curr
-                .connections_via(tile)
-                .iter()
-                .filter(|n| n.is_some())
-                .map(|n| n.unwrap())
-                .collect::<Vec<Node>>()
-                .try_into()
-                .unwrap();
+            connections = curr.connections_via(tile).into_iter().flatten().collect();
 
             if connections.len() == 2 {
                 // This means it's not at the edge pointing out into space
@@ -92,95 +83,107 @@ fn build_connection_table() -> (Node, HashMap<Node, Vec<Node>>) {
         }
     }
 
-    return (start, conn_by_node);
+    (start, conn_by_node)
 
 }
 
-fn build_graph(conn_by_node: HashMap<Node, Vec<Node>>, start: Node) -> (UnGraph<Node, ()>, NodeIndex) {
-    let mut g = UnGraph::<Node, ()>::default();
-    let mut our_idx: NodeIndex;
-    let mut start_idx: NodeIndex = NodeIndex::from(0);
-    let mut node_idx_by_node: HashMap<Node, NodeIndex> = HashMap::new();
-    let mut connection_idxs: Vec<NodeIndex>;
-    let mut new_connection_idxs: Vec<NodeIndex>;
-
-    for (n, conns) in conn_by_node.iter() {
-        let left = conns.first().unwrap();
-        let right = conns.last().unwrap();
-
-        if *left != start && *right != start {
-            if !conn_by_node.contains_key(left) || !conn_by_node.contains_key(right) {
-                // our left/right nodes don't actually exist, skip!
-                continue;
-            }
-
-            let left_neighbors = conn_by_node.get(left).unwrap();
-            let right_neighbors = conn_by_node.get(right).unwrap();
-            if !left_neighbors.contains(&n) || !right_neighbors.contains(&n) {
-                // n thinks it connects to left/right, but left/right doesn't doesn't connect back to n.
-                // make a special case for the start node since it doesn't know which way it connects.
-                // Either way, this pipe is terminated on one side, so skip it since it can't be part of the loop.
-                continue;
-            }
-        }
-
-        our_idx = *node_idx_by_node.entry(*n).or_insert_with(|| g.add_node(*n));
-        if *n == start {
-            start_idx = our_idx;
-        }
-        connection_idxs = conns
-            .iter()
-            .map(|n| *node_idx_by_node.entry(*n).or_insert_with(|| g.add_node(*n)))
-            .collect();
-
-        new_connection_idxs = connection_idxs
-            .iter()
-            .filter(|n_idx| !g.contains_edge(our_idx, **n_idx))
-            .map(|n| *n)
-            .collect::<Vec<NodeIndex>>();
-
-        for idx in new_connection_idxs {
-            g.add_edge(our_idx, idx, ());
-        }
-    }
-    (g, start_idx)
+fn part1() {
+    done!(DAY, 1, farthest_from_start(crate::utils::lines(DAY)));
 }
 
-fn part1() {
+fn farthest_from_start(lines: impl Iterator<Item = String>) -> usize {
     /*
      Real simple two step process:
      1. Record all the pipes and what they think they connect to
-     2. Add to the graph only the pipes that actually connect to what they think they do
+     2. Only follow pipes that actually connect to what they think they do
           that is, for any given node, it's contained in *both* of its neighbors set of connections
-     3. Find the start element, get all paths from it's first neighbor to it's second neighbor. There should only be one.
-     4. "furthest" point is (distance walked + 1) / 2
+          make a special case for the start node since it doesn't know which way it connects.
+          More than two pipes can point at it, but only two of them lead round and back to it.
+     3. Walk outwards from the start in both directions at once (BFS)
+     4. "furthest" point is whatever the walk reached last
     */
 
     // Step 1
-    let (start, conn_by_node) = build_connection_table();
+    let (start, conn_by_node) = build_connection_table(lines);
 
     // Step 2
-    let (g, start_idx) = build_graph(conn_by_node, start);
+    let connects_back = |from: &Node, to: &Node| {
+        *to == start || conn_by_node.get(to).is_some_and(|c| c.contains(from))
+    };
+    // Follow the pipes from `first` and, if they come back round to the start, the last one before it
+    let loops_back = |first: Node| {
+        let (mut prev, mut curr) = (start, first);
+        loop {
+            let next = *conn_by_node.get(&curr)?.iter().find(|n| **n != prev)?;
+            if !connects_back(&curr, &next) {
+                return None;
+            }
+            if next == start {
+                return Some(curr);
+            }
+            (prev, curr) = (curr, next);
+        }
+    };
+    let start_neighbors: Vec<Node> = conn_by_node
+        .iter()
+        .filter(|(_, conns)| conns.contains(&start))
+        .find_map(|(n, _)| loops_back(*n).map(|last| vec![*n, last]))
+        .expect("The start isn't on a loop!");
 
     // Step 3
-    let neighbors: Vec<NodeIndex> = g.neighbors(start_idx).collect();
-    let all_paths = all_simple_paths::<Vec<_>, _>(
-        &g,
-        *neighbors.first().unwrap(),
-        *neighbors.last().unwrap(),
-        2,
-        None,
-    )
-    .collect::<Vec<_>>();
-
-    assert!(
-        all_paths.len() == 1,
-        "There should only be one path from the start!"
+    let walk = bfs(
+        start,
+        |n| {
+            if *n == start {
+                return start_neighbors.clone();
+            }
+            conn_by_node[n]
+                .iter()
+                .filter(|m| connects_back(n, m))
+                .copied()
+                .collect()
+        },
+        |_| false,
     );
 
-    done!(DAY, 1, (all_paths.first().unwrap().len() + 1) / 2);
+    // Step 4
+    *walk.dist.values().max().unwrap()
 }
 
 fn part2() {
     done!(DAY, 2, "idk");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(rows: &[&str]) -> impl Iterator<Item = String> {
+        rows.iter()
+            .map(|r| r.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn simple_loop() {
+        assert_eq!(
+            farthest_from_start(grid(&[".....", ".S-7.", ".|.|.", ".L-J.", "....."])),
+            4
+        );
+    }
+
+    #[test]
+    fn complex_loop_with_junk() {
+        assert_eq!(
+            farthest_from_start(grid(&["7-F7-", ".FJ|7", "SJLL7", "|F--J", "LJ.LJ"])),
+            8
+        );
+    }
+
+    #[test]
+    fn dead_end_spur_off_the_start() {
+        // The dashes point at S but never come back to it
+        assert_eq!(farthest_from_start(grid(&["-------S7.", ".......LJ."])), 2);
+    }
+}
@@ -1,86 +1,676 @@
 const DAY: u8 = 2;
 
 pub fn main() {
-    part1();
-    part2();
+    let games = get_games();
+
+    // e.g. `cargo run -- 2 --bag "12 red, 13 green, 14 blue, 2 purple"`
+    let bag: Bag = crate::utils::arg_value("--bag")
+        .unwrap_or("12 red, 13 green, 14 blue".to_string())
+        .parse()
+        .unwrap_or_else(|e| panic!("Bad bag: {}", e));
+
+    part1(&games, &bag, crate::utils::has_flag("--violations"));
+    part2(&games);
+
+    // e.g. `cargo run -- 2 --min-bag 1,5,7` or `--min-bag all`
+    if let Some(ids) = crate::utils::arg_value("--min-bag") {
+        let ids: Option<Vec<u32>> = (ids != "all").then(|| {
+            ids.split(',')
+                .map(|id| {
+                    id.trim()
+                        .parse::<u32>()
+                        .expect("Game ids should be numbers")
+                })
+                .collect()
+        });
+        println!(
+            "Smallest bag for games {}: {}",
+            ids.as_ref()
+                .map_or("all".to_string(), |i| format!("{:?}", i)),
+            games.min_bag(ids.as_deref())
+        );
+    }
+
+    if crate::utils::has_flag("--sensitivity") {
+        for (colour, steps) in games.breakpoints(&bag) {
+            let steps: Vec<String> = steps
+                .iter()
+                .map(|b| format!("{}+ => {}", b.limit, b.id_sum))
+                .collect();
+            println!(
+                "{} (bag has {}): {}",
+                colour,
+                bag.count(&colour),
+                steps.join(", ")
+            );
+        }
+    }
+
+    // e.g. `cargo run -- 2 --estimate 60` for the likeliest bag of at most 60 cubes
+    if let Some(budget) = crate::utils::arg_value("--estimate") {
+        let budget = budget
+            .parse::<u32>()
+            .expect("The budget should be a number of cubes");
+        match games.most_likely_bag(budget) {
+            Some(estimate) => println!(
+                "Most likely bag of at most {} cubes: {} (log likelihood {:.3})",
+                budget, estimate.bag, estimate.log_likelihood
+            ),
+            None => println!(
+                "No bag of at most {} cubes could produce every game",
+                budget
+            ),
+        }
+    }
 }
 
-use std::collections::HashMap;
+use std::{collections::BTreeMap, error::Error, fmt, ops::Deref, str::FromStr};
 
-fn get_game_index(raw_line: &str) -> u128 {
-    let first_cut: Vec<&str> = raw_line.split(": ").collect();
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DrawError {
+    Empty,
+    UnknownSeparator(char),
+    BadPull(String),
+    BadCount(String),
+    DuplicateColour(String),
+}
 
-    let game_info: Vec<&str> = first_cut.first().unwrap().split(" ").collect();
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameError {
+    MissingHeader,
+    BadId(String),
+    BadDraw { draw: usize, error: DrawError },
+}
 
-    game_info.last().unwrap().parse::<u128>().unwrap()
+impl fmt::Display for DrawError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DrawError::Empty => write!(f, "it's empty"),
+            DrawError::UnknownSeparator(c) => write!(f, "unexpected {:?}", c),
+            DrawError::BadPull(pull) => write!(f, "{:?} isn't \"<count> <colour>\"", pull),
+            DrawError::BadCount(count) => write!(f, "{:?} isn't a count of cubes", count),
+            DrawError::DuplicateColour(colour) => write!(f, "{} shows up more than once", colour),
+        }
+    }
 }
 
-fn game_is_valid(raw_line: &str, cube_counts: &HashMap<&str, u8>) -> bool {
-    let first_cut: Vec<&str> = raw_line.split(": ").collect();
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameError::MissingHeader => write!(f, "doesn't start with \"Game <id>: \""),
+            GameError::BadId(id) => write!(f, "{:?} isn't a game id", id),
+            GameError::BadDraw { draw, error } => write!(f, "draw {}: {}", draw + 1, error),
+        }
+    }
+}
 
-    let draw_strs: Vec<&str> = first_cut.last().unwrap().split("; ").collect();
+impl Error for DrawError {}
+impl Error for GameError {}
+
+/// Some number of cubes of each colour, e.g. "3 blue, 4 red".
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cubes {
+    counts: BTreeMap<String, u32>,
+}
 
-    for draw in draw_strs {
-        let color_pulls: Vec<&str> = draw.split(", ").collect();
-        for pull in color_pulls {
-            let pull_info: Vec<&str> = pull.split(" ").collect();
-            let count = pull_info.first().unwrap().parse::<u8>().unwrap();
-            let color = pull_info.last().unwrap();
+/// One handful of cubes pulled out of the bag
+pub type Draw = Cubes;
 
-            if count > *cube_counts.get(color).unwrap() {
-                return false;
+/// Everything that's in the bag to begin with
+pub type Bag = Cubes;
+
+/// A draw that pulled out more of a colour than the bag holds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub game: u32,
+    pub draw: usize,
+    pub colour: String,
+    pub seen: u32,
+    pub allowed: u32,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Game {}, draw {}: {} {} but the bag only has {}",
+            self.game,
+            self.draw + 1,
+            self.seen,
+            self.colour,
+            self.allowed
+        )
+    }
+}
+
+impl Cubes {
+    /// How many of `colour` there are. Colours we've never heard of are zero.
+    pub fn count(&self, colour: &str) -> u32 {
+        *self.counts.get(colour).unwrap_or(&0)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, u32)> {
+        self.counts.iter().map(|(c, n)| (c.as_str(), *n))
+    }
+
+    /// All the counts multiplied together
+    pub fn power(&self) -> u128 {
+        self.counts
+            .values()
+            .fold(1_u128, |acc, n| acc * (*n as u128))
+    }
+}
+
+impl FromStr for Cubes {
+    type Err = DrawError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().is_empty() {
+            return Err(DrawError::Empty);
+        }
+
+        let mut cubes = Cubes::default();
+
+        for pull in s.split(", ") {
+            if let Some(c) = pull
+                .chars()
+                .find(|c| !c.is_ascii_alphanumeric() && *c != ' ')
+            {
+                return Err(DrawError::UnknownSeparator(c));
+            }
+
+            let bad_pull = || DrawError::BadPull(pull.to_string());
+            let (count, colour) = pull.split_once(' ').ok_or_else(bad_pull)?;
+            if colour.is_empty() || !colour.chars().all(|c| c.is_ascii_alphabetic()) {
+                return Err(bad_pull());
+            }
+
+            let count = count
+                .parse::<u32>()
+                .map_err(|_| DrawError::BadCount(count.to_string()))?;
+
+            if cubes.counts.insert(colour.to_string(), count).is_some() {
+                return Err(DrawError::DuplicateColour(colour.to_string()));
             }
         }
+
+        Ok(cubes)
     }
+}
 
-    true
+impl fmt::Display for Cubes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let pulls: Vec<String> = self.iter().map(|(c, n)| format!("{} {}", n, c)).collect();
+        write!(f, "{}", pulls.join(", "))
+    }
 }
 
-fn get_game_power(raw_line: &str) -> u128 {
-    let first_cut: Vec<&str> = raw_line.split(": ").collect();
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Game {
+    pub id: u32,
+    pub draws: Vec<Draw>,
+}
 
-    let draw_strs: Vec<&str> = first_cut.last().unwrap().split("; ").collect();
+impl Game {
+    /// Whether every draw could have come out of `bag`.
+    pub fn is_possible_with(&self, bag: &Bag) -> bool {
+        self.draws
+            .iter()
+            .all(|d| d.iter().all(|(colour, n)| n <= bag.count(colour)))
+    }
 
-    let mut min_seen: HashMap<&str, u8> = HashMap::new();
+    /// Every colour, in every draw, that `bag` couldn't have produced.
+    pub fn violations(&self, bag: &Bag) -> Vec<Violation> {
+        self.draws
+            .iter()
+            .enumerate()
+            .flat_map(|(draw, d)| {
+                d.iter()
+                    .filter(|(colour, n)| *n > bag.count(colour))
+                    .map(move |(colour, seen)| Violation {
+                        game: self.id,
+                        draw,
+                        colour: colour.to_string(),
+                        seen,
+                        allowed: bag.count(colour),
+                    })
+            })
+            .collect()
+    }
 
-    for draw in draw_strs {
-        let color_pulls: Vec<&str> = draw.split(", ").collect();
-        for pull in color_pulls {
-            let pull_info: Vec<&str> = pull.split(" ").collect();
-            let count = pull_info.first().unwrap().parse::<u8>().unwrap();
-            let color = pull_info.last().unwrap();
+    /// The fewest cubes of each colour that could have produced every draw.
+    pub fn min_bag(&self) -> Bag {
+        let mut min_seen = Cubes::default();
 
-            if min_seen.contains_key(color) {
-                if count > *(min_seen.get(color).unwrap()) {
-                    min_seen.insert(&color, count);
-                }
-            } else {
-                min_seen.insert(&color, count);
+        for draw in &self.draws {
+            for (colour, n) in draw.iter() {
+                let seen = min_seen.counts.entry(colour.to_string()).or_insert(0);
+                *seen = (*seen).max(n);
             }
         }
+
+        min_seen
+    }
+
+    /*
+      How likely this game is with a given bag, treating each draw as grabbing
+      that many cubes at once out of the full bag (and putting them back after).
+
+      For one draw that's the multivariate hypergeometric distribution:
+          P(draw) = product over colours of C(bag[colour], drawn[colour]) / C(bag total, drawn total)
+
+      Probabilities of a whole game get tiny fast, so this is the natural log of
+      the probability. An impossible game is negative infinity.
+    */
+    pub fn log_likelihood(&self, bag: &Bag) -> f64 {
+        let total: u32 = bag.counts.values().sum();
+        let ln_fact = ln_factorials(total);
+
+        self.draws
+            .iter()
+            .map(|d| {
+                let drawn: u32 = d.iter().map(|(_, n)| n).sum();
+                d.iter()
+                    .map(|(colour, n)| ln_choose(&ln_fact, bag.count(colour), n))
+                    .sum::<f64>()
+                    - ln_choose(&ln_fact, total, drawn)
+            })
+            .sum()
+    }
+}
+
+/// ln(n!) for every n in 0..=max
+fn ln_factorials(max: u32) -> Vec<f64> {
+    let mut ln_fact = vec![0.0_f64; max as usize + 1];
+    for n in 1..ln_fact.len() {
+        ln_fact[n] = ln_fact[n - 1] + (n as f64).ln();
+    }
+    ln_fact
+}
+
+/// ln(C(n, k)), or negative infinity if there's no way to pick k from n.
+fn ln_choose(ln_fact: &[f64], n: u32, k: u32) -> f64 {
+    if k > n {
+        return f64::NEG_INFINITY;
     }
+    ln_fact[n as usize] - ln_fact[k as usize] - ln_fact[(n - k) as usize]
+}
+
+/// With at least `limit` of some colour in the bag, part 1 would be `id_sum`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub limit: u32,
+    pub id_sum: u128,
+}
+
+#[derive(Debug, Clone)]
+pub struct Estimate {
+    pub bag: Bag,
+    pub log_likelihood: f64,
+}
+
+/// Every game we've been told about. Queries that need to look across games live here.
+#[derive(Debug, Clone)]
+pub struct Games {
+    games: Vec<Game>,
+}
 
-    min_seen
-        .values()
-        .fold(1 as u128, |acc, e| acc * (*e as u128))
+impl Deref for Games {
+    type Target = [Game];
+
+    fn deref(&self) -> &Self::Target {
+        &self.games
+    }
 }
 
-fn part1() {
-    let cube_counts: HashMap<&str, u8> = HashMap::from([("red", 12), ("green", 13), ("blue", 14)]);
+impl Games {
+    pub fn new(games: Vec<Game>) -> Self {
+        Self { games }
+    }
+
+    /// Every game that `bag` could have produced.
+    pub fn possible_with<'a>(&'a self, bag: &'a Bag) -> impl Iterator<Item = &'a Game> {
+        self.games.iter().filter(|g| g.is_possible_with(bag))
+    }
+
+    /// The smallest bag that makes every game possible, or just the games in `ids` if given.
+    pub fn min_bag(&self, ids: Option<&[u32]>) -> Bag {
+        let mut min_seen = Bag::default();
 
-    let our_sum = crate::utils::lines(DAY).fold(0, |acc, l| {
-        acc + if game_is_valid(&l, &cube_counts) {
-            get_game_index(&l)
-        } else {
-            0
+        for game in self
+            .games
+            .iter()
+            .filter(|g| ids.is_none_or(|ids| ids.contains(&g.id)))
+        {
+            for (colour, n) in game.min_bag().iter() {
+                let seen = min_seen.counts.entry(colour.to_string()).or_insert(0);
+                *seen = (*seen).max(n);
+            }
+        }
+
+        min_seen
+    }
+
+    /*
+      For each colour, the limits at which the part 1 answer changes if we only
+      change how many of that colour are in `bag`.
+
+      Only games that already fit the bag for every *other* colour can ever be
+      affected, and each of those becomes possible exactly when the limit
+      reaches the most of this colour it ever drew. So sorting those tells us
+      every step, along with the sum of ids from that limit upwards.
+    */
+    pub fn breakpoints(&self, bag: &Bag) -> BTreeMap<String, Vec<Breakpoint>> {
+        let mut colours: Vec<&str> = bag.iter().map(|(c, _)| c).collect();
+        colours.extend(
+            self.games
+                .iter()
+                .flat_map(|g| g.draws.iter().flat_map(|d| d.iter().map(|(c, _)| c))),
+        );
+        colours.sort();
+        colours.dedup();
+
+        let needs: Vec<(u32, Bag)> = self.games.iter().map(|g| (g.id, g.min_bag())).collect();
+
+        colours
+            .into_iter()
+            .map(|colour| {
+                let mut affected: Vec<(u32, u32)> = needs
+                    .iter()
+                    .filter(|(_, need)| need.iter().all(|(c, n)| c == colour || n <= bag.count(c)))
+                    .map(|(id, need)| (need.count(colour), *id))
+                    .collect();
+                affected.sort();
+
+                let mut steps: Vec<Breakpoint> = vec![Breakpoint {
+                    limit: 0,
+                    id_sum: 0,
+                }];
+                for (limit, id) in affected {
+                    let last = steps.last_mut().unwrap();
+                    if last.limit == limit {
+                        last.id_sum += id as u128;
+                    } else {
+                        let id_sum = last.id_sum + id as u128;
+                        steps.push(Breakpoint { limit, id_sum });
+                    }
+                }
+
+                (colour.to_string(), steps)
+            })
+            .collect()
+    }
+
+    /// Log likelihood of seeing every game, assuming they're independent.
+    pub fn log_likelihood(&self, bag: &Bag) -> f64 {
+        self.games.iter().map(|g| g.log_likelihood(bag)).sum()
+    }
+
+    /*
+      The bag with at most `budget` cubes that makes every game we've seen the
+      most likely. None if even the smallest bag that could produce them all
+      is over budget.
+
+      The log likelihood splits into a part for each colour, which only depends
+      on how many of that colour are in the bag, minus a part that only depends
+      on the bag's total:
+          sum over colours of f_colour(bag[colour])  -  g(total)
+
+      So for each total we find the best way to share it out between colours
+      with a knapsack over colours, and then pick the best total.
+    */
+    pub fn most_likely_bag(&self, budget: u32) -> Option<Estimate> {
+        let ln_fact = ln_factorials(budget);
+        let size = budget as usize + 1;
+        let draws: Vec<&Draw> = self.games.iter().flat_map(|g| g.draws.iter()).collect();
+
+        let mut colours: Vec<&str> = draws
+            .iter()
+            .flat_map(|d| d.iter().map(|(c, _)| c))
+            .collect();
+        colours.sort();
+        colours.dedup();
+
+        // g(total)
+        let total_part: Vec<f64> = (0..=budget)
+            .map(|total| {
+                draws
+                    .iter()
+                    .map(|d| ln_choose(&ln_fact, total, d.iter().map(|(_, n)| n).sum()))
+                    .sum()
+            })
+            .collect();
+
+        // best[total] is the best sum of f_colour over the colours so far, choice[colour][total] how we got it
+        let mut best: Vec<f64> = vec![f64::NEG_INFINITY; size];
+        best[0] = 0.0;
+        let mut choice: Vec<Vec<usize>> = Vec::new();
+
+        for colour in &colours {
+            let colour_part: Vec<f64> = (0..=budget)
+                .map(|count| {
+                    draws
+                        .iter()
+                        .map(|d| ln_choose(&ln_fact, count, d.count(colour)))
+                        .sum()
+                })
+                .collect();
+
+            let mut next_best = vec![f64::NEG_INFINITY; size];
+            let mut next_choice = vec![0; size];
+            for total in 0..size {
+                for count in 0..=total {
+                    let candidate = colour_part[count] + best[total - count];
+                    if candidate > next_best[total] {
+                        next_best[total] = candidate;
+                        next_choice[total] = count;
+                    }
+                }
+            }
+
+            best = next_best;
+            choice.push(next_choice);
+        }
+
+        let (mut total, log_likelihood) = (0..size)
+            .filter(|t| best[*t].is_finite() && total_part[*t].is_finite())
+            .map(|t| (t, best[t] - total_part[t]))
+            .max_by(|a, b| a.1.total_cmp(&b.1))?;
+
+        let mut bag = Bag::default();
+        for (idx, colour) in colours.iter().enumerate().rev() {
+            let count = choice[idx][total];
+            bag.counts.insert(colour.to_string(), count as u32);
+            total -= count;
         }
-    });
+
+        Some(Estimate {
+            bag,
+            log_likelihood,
+        })
+    }
+}
+
+impl FromStr for Game {
+    type Err = GameError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (header, draws) = s.split_once(": ").ok_or(GameError::MissingHeader)?;
+        let id = header
+            .strip_prefix("Game ")
+            .ok_or(GameError::MissingHeader)?;
+
+        Ok(Game {
+            id: id
+                .parse::<u32>()
+                .map_err(|_| GameError::BadId(id.to_string()))?,
+            draws: draws
+                .split("; ")
+                .enumerate()
+                .map(|(draw, d)| {
+                    d.parse::<Draw>()
+                        .map_err(|error| GameError::BadDraw { draw, error })
+                })
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+fn get_games() -> Games {
+    Games::new(
+        crate::utils::lines(DAY)
+            .enumerate()
+            .map(|(idx, l)| {
+                l.parse::<Game>()
+                    .unwrap_or_else(|e| panic!("Line {}: {}", idx + 1, e))
+            })
+            .collect(),
+    )
+}
+
+fn part1(games: &Games, bag: &Bag, show_violations: bool) {
+    let our_sum: u128 = games.possible_with(bag).map(|g| g.id as u128).sum();
 
     done!(DAY, 1, our_sum);
+
+    if show_violations {
+        for violation in games.iter().flat_map(|g| g.violations(bag)) {
+            println!("        {}", violation);
+        }
+    }
 }
 
-fn part2() {
-    let our_sum = crate::utils::lines(DAY).fold(0, |acc, l| acc + get_game_power(&l));
+fn part2(games: &[Game]) {
+    let our_sum: u128 = games.iter().map(|g| g.min_bag().power()).sum();
 
     done!(DAY, 2, our_sum);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
+Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 8 green, 1 blue
+Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
+Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red
+Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green";
+
+    fn example() -> Games {
+        Games::new(EXAMPLE.lines().map(|l| l.parse().unwrap()).collect())
+    }
+
+    fn bag(red: u32, green: u32, blue: u32) -> Bag {
+        format!("{} red, {} green, {} blue", red, green, blue)
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn bad_draws() {
+        let parse = |s: &str| s.parse::<Draw>();
+        assert_eq!(parse(""), Err(DrawError::Empty));
+        assert_eq!(parse("   "), Err(DrawError::Empty));
+        assert_eq!(parse("3 blue,4 red"), Err(DrawError::UnknownSeparator(',')));
+        assert_eq!(parse("3 blue/4 red"), Err(DrawError::UnknownSeparator('/')));
+        assert_eq!(
+            parse("3 blue, 4 red, 1 blue"),
+            Err(DrawError::DuplicateColour("blue".to_string()))
+        );
+        assert_eq!(parse("x blue"), Err(DrawError::BadCount("x".to_string())));
+        assert_eq!(
+            parse("99999999999 red"),
+            Err(DrawError::BadCount("99999999999".to_string()))
+        );
+        assert_eq!(parse("3"), Err(DrawError::BadPull("3".to_string())));
+    }
+
+    #[test]
+    fn bad_draws_say_which_draw() {
+        assert_eq!(
+            "Game 7: 3 blue; ; 4 red".parse::<Game>(),
+            Err(GameError::BadDraw {
+                draw: 1,
+                error: DrawError::Empty
+            })
+        );
+        assert_eq!(
+            "Game 7: 3 blue;4 red".parse::<Game>(),
+            Err(GameError::BadDraw {
+                draw: 0,
+                error: DrawError::UnknownSeparator(';')
+            })
+        );
+        assert_eq!(
+            "Game x: 3 blue".parse::<Game>(),
+            Err(GameError::BadId("x".to_string()))
+        );
+    }
+
+    #[test]
+    fn big_counts_parse() {
+        let draw: Draw = "300 red, 1 blue".parse().unwrap();
+        assert_eq!(draw.count("red"), 300);
+        assert_eq!(draw.count("blue"), 1);
+        assert_eq!(draw.count("green"), 0);
+    }
+
+    #[test]
+    fn log_likelihood_by_hand() {
+        let bag: Bag = "2 red, 1 blue".parse().unwrap();
+        // 2 of the 3 cubes are red, then 1 of the 3 is blue
+        let game: Game = "Game 1: 1 red; 1 blue".parse().unwrap();
+        assert!((game.log_likelihood(&bag) - (2.0_f64 / 9.0).ln()).abs() < 1e-12);
+
+        // Both reds at once is 1 way out of C(3, 2)
+        let game: Game = "Game 1: 2 red".parse().unwrap();
+        assert!((game.log_likelihood(&bag) - (1.0_f64 / 3.0).ln()).abs() < 1e-12);
+
+        let game: Game = "Game 1: 3 red".parse().unwrap();
+        assert_eq!(game.log_likelihood(&bag), f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn most_likely_bag_matches_exhaustive_search() {
+        let games = example();
+        // The smallest bag that fits every game is 20 red, 13 green, 15 blue
+        assert!(games.most_likely_bag(47).is_none());
+
+        for budget in [48, 51] {
+            let mut best = f64::NEG_INFINITY;
+            for red in 0..=budget {
+                for green in 0..=budget - red {
+                    for blue in 0..=budget - red - green {
+                        best = best.max(games.log_likelihood(&bag(red, green, blue)));
+                    }
+                }
+            }
+
+            let estimate = games.most_likely_bag(budget).unwrap();
+            let cubes: u32 = estimate.bag.iter().map(|(_, n)| n).sum();
+            assert!(cubes <= budget);
+            assert!((estimate.log_likelihood - best).abs() < 1e-9);
+            assert!((games.log_likelihood(&estimate.bag) - best).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn breakpoints_match_recomputing_part_1() {
+        let games = example();
+        let bag = bag(12, 13, 14);
+        let id_sum = |bag: &Bag| -> u128 { games.possible_with(bag).map(|g| g.id as u128).sum() };
+
+        for (colour, steps) in games.breakpoints(&bag) {
+            assert_eq!(steps[0].limit, 0);
+            // Every limit up to past the last step, not just the steps themselves
+            for limit in 0..=steps.last().unwrap().limit + 2 {
+                let mut changed = bag.clone();
+                changed.counts.insert(colour.clone(), limit);
+
+                let step = steps.iter().rev().find(|b| b.limit <= limit).unwrap();
+                assert_eq!(step.id_sum, id_sum(&changed), "{} at {}", colour, limit);
+            }
+        }
+    }
+}
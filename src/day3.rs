@@ -1,54 +1,233 @@
 const DAY: u8 = 3;
 
 pub fn main() {
-    part1();
-    part2();
-}
+    // e.g. `cargo run -- 3 --rules "symbols=*#; gear=*:2:product; gear=#:3:sum; adjacency=4"`
+    let rules: Rules = crate::utils::arg_value("--rules")
+        .map(|r| r.parse().unwrap_or_else(|e| panic!("Bad rules: {}", e)))
+        .unwrap_or_default();
 
-use std::fmt;
+    // e.g. `cargo run -- 3 --stress 70000x20` to try out a generated schematic instead of ours
+    if let Some(size) = crate::utils::arg_value("--stress") {
+        stress(&size, rules);
+        return;
+    }
 
-#[derive(Debug, Default)]
-struct Symbol {
-    x: u16,
-    y: u16,
-    val: char,
+    let schematic =
+        Schematic::parse(crate::utils::lines(DAY), rules).unwrap_or_else(|e| panic!("{}", e));
+
+    if crate::utils::has_flag("--render") {
+        print!("{}", schematic.render());
+    }
+
+    // e.g. `cargo run -- 3 --edit "5,2,*;7,0,."` to edit ours and see the totals change
+    if let Some(edits) = crate::utils::arg_value("--edit") {
+        edit(schematic, &edits);
+        return;
+    }
+
+    part1(&schematic);
+    part2(&schematic);
 }
 
-#[derive(Debug, Default)]
-struct PartNumber {
-    row: u16,
-    start_col: u16,
-    end_col: u16,
-    number: u16,
-    left_bound: u16,
-    right_bound: u16,
-    top_bound: u16,
-    lower_bound: u16,
+use crate::utils::ansi;
+use num_bigint::BigUint;
+use std::{collections::HashMap, error::Error, fmt, str::FromStr, time::Instant};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SchematicError {
+    NumberTooLarge {
+        row: usize,
+        col: usize,
+        digits: String,
+    },
+    OffTheSchematic {
+        x: usize,
+        y: usize,
+    },
+    TotalTooLarge {
+        what: String,
+    },
 }
 
-impl fmt::Display for Symbol {
+impl fmt::Display for SchematicError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "({:>3}, {:>3})", self.x, self.y)
+        match self {
+            SchematicError::NumberTooLarge { row, col, digits } => write!(
+                f,
+                "The number at ({}, {}) is too big for a part number (max {}): {}",
+                col,
+                row,
+                u64::MAX,
+                digits
+            ),
+            SchematicError::OffTheSchematic { x, y } => {
+                write!(f, "({}, {}) isn't on the schematic", x, y)
+            }
+            SchematicError::TotalTooLarge { what } => {
+                write!(f, "The {} doesn't fit in a u128 (max {})", what, u128::MAX)
+            }
+        }
+    }
+}
+
+impl Error for SchematicError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Adjacency {
+    /// Diagonals count
+    Eight,
+    /// Only up, down, left and right
+    Four,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Aggregate {
+    Product,
+    Sum,
+    Max,
+}
+
+impl Aggregate {
+    /// None if the answer doesn't fit in a u128, e.g. a product of three big numbers.
+    fn apply(&self, nums: &[&PartNumber]) -> Option<u128> {
+        let mut values = nums.iter().map(|n| n.number as u128);
+        match self {
+            Aggregate::Product => values.try_fold(1_u128, |acc, n| acc.checked_mul(n)),
+            Aggregate::Sum => values.try_fold(0_u128, |acc, n| acc.checked_add(n)),
+            Aggregate::Max => Some(values.max().unwrap_or(0)),
+        }
+    }
+}
+
+/// A symbol that touches exactly `neighbours` numbers is worth `aggregate` of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct GearRule {
+    symbol: char,
+    neighbours: usize,
+    aggregate: Aggregate,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rules {
+    /// Which symbols make a number a part number. None means anything that isn't a '.' or digit.
+    part_symbols: Option<Vec<char>>,
+    gears: Vec<GearRule>,
+    adjacency: Adjacency,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            part_symbols: None,
+            gears: vec![GearRule {
+                symbol: '*',
+                neighbours: 2,
+                aggregate: Aggregate::Product,
+            }],
+            adjacency: Adjacency::Eight,
+        }
+    }
+}
+
+impl Rules {
+    fn is_part_symbol(&self, c: char) -> bool {
+        match &self.part_symbols {
+            None => c != '.' && !c.is_ascii_digit(),
+            Some(symbols) => symbols.contains(&c),
+        }
+    }
+
+    fn is_gear_symbol(&self, c: char) -> bool {
+        self.gears.iter().any(|g| g.symbol == c)
     }
 }
 
-impl Symbol {
-    fn get_gear_ratio(self: &Symbol, nums: &Vec<PartNumber>) -> u128 {
-        let gears: Vec<&PartNumber> = nums.iter().filter(|n| n.is_adjacent_to(self)).collect();
+/*
+  `; ` separated `key=value`s, anything left out keeps its default:
+    symbols=any or symbols=*#$     which symbols mark part numbers
+    gear=*:2:product               can be given more than once, replaces the default gear
+    adjacency=8 or adjacency=4
+*/
+impl FromStr for Rules {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rules = Rules::default();
+        let mut gears: Vec<GearRule> = Vec::new();
+
+        for clause in s.split(';').map(|c| c.trim()).filter(|c| !c.is_empty()) {
+            let (key, value) = clause
+                .split_once('=')
+                .ok_or(format!("{:?} isn't key=value", clause))?;
+            let value = value.trim();
+
+            match key.trim() {
+                "symbols" if value == "any" => rules.part_symbols = None,
+                "symbols" => rules.part_symbols = Some(value.chars().collect()),
+                "adjacency" => {
+                    rules.adjacency = match value {
+                        "8" => Adjacency::Eight,
+                        "4" => Adjacency::Four,
+                        _ => return Err(format!("adjacency is 8 or 4, not {:?}", value)),
+                    }
+                }
+                "gear" => {
+                    let bad_gear =
+                        || format!("{:?} isn't <symbol>:<neighbours>:<product|sum|max>", value);
+                    let parts: Vec<&str> = value.split(':').map(str::trim).collect();
+                    let [symbol, neighbours, aggregate] = parts[..] else {
+                        return Err(bad_gear());
+                    };
+                    let mut symbol = symbol.chars();
+
+                    gears.push(GearRule {
+                        symbol: match (symbol.next(), symbol.next()) {
+                            (Some(c), None) => c,
+                            _ => return Err(bad_gear()),
+                        },
+                        neighbours: neighbours.parse().map_err(|_| bad_gear())?,
+                        aggregate: match aggregate {
+                            "product" => Aggregate::Product,
+                            "sum" => Aggregate::Sum,
+                            "max" => Aggregate::Max,
+                            _ => return Err(bad_gear()),
+                        },
+                    });
+                }
+                other => return Err(format!("Unknown rule {:?}", other)),
+            }
+        }
 
-        if gears.len() != 2 {
-            return 0;
+        if !gears.is_empty() {
+            rules.gears = gears;
         }
 
-        gears.first().unwrap().number as u128 * gears.last().unwrap().number as u128
+        Ok(rules)
     }
 }
 
-impl PartNumber {
-    fn is_adjacent_to(self: &PartNumber, pos: &Symbol) -> bool {
-        // Adjacent means that all of these must be true:
-        (pos.x >= self.left_bound && pos.x <= self.right_bound)
-            && (pos.y >= self.lower_bound && pos.y <= self.top_bound)
+#[derive(Debug, Default)]
+struct Symbol {
+    x: usize,
+    y: usize,
+    val: char,
+}
+
+#[derive(Debug, Default)]
+struct PartNumber {
+    row: usize,
+    start_col: usize,
+    end_col: usize,
+    number: u64,
+    left_bound: usize,
+    right_bound: usize,
+    top_bound: usize,
+    lower_bound: usize,
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({:>3}, {:>3})", self.x, self.y)
     }
 }
 
@@ -62,97 +241,1065 @@ impl fmt::Display for PartNumber {
     }
 }
 
-fn find_numbers_and_symbols() -> (Vec<PartNumber>, Vec<Symbol>) {
-    let mut curr_num = String::new();
-    let mut in_num = false;
-    let mut nums: Vec<PartNumber> = Vec::new();
-    let mut syms: Vec<Symbol> = Vec::new();
-    let mut curr_part = PartNumber::default();
+/*
+  The whole schematic, parsed once.
+
+  Alongside the numbers and symbols we keep a grid saying which number (if any)
+  owns each cell, so "what's next to this?" is a handful of lookups around it
+  instead of a scan over everything.
+*/
+#[derive(Debug, Default)]
+struct Schematic {
+    width: usize,
+    height: usize,
+    cells: Vec<char>,
+    // Index into nums for every cell that's part of a number
+    owners: Vec<Option<usize>>,
+    // Index into syms for every cell that's a symbol
+    sym_owners: Vec<Option<usize>>,
+    nums: Vec<PartNumber>,
+    syms: Vec<Symbol>,
+    rules: Rules,
+}
+
+impl Schematic {
+    fn parse(lines: impl Iterator<Item = String>, rules: Rules) -> Result<Self, SchematicError> {
+        let lines: Vec<Vec<char>> = lines.map(|l| l.chars().collect()).collect();
+        let width = lines.iter().map(|l| l.len()).max().unwrap_or(0);
 
-    for (line_no, line) in crate::utils::lines(DAY).enumerate() {
-        for (col_no, our_char) in line.chars().enumerate() {
-            if our_char.is_ascii_digit() {
-                curr_num.push(our_char);
+        let mut schematic = Schematic {
+            width,
+            height: lines.len(),
+            cells: vec!['.'; width * lines.len()],
+            owners: vec![None; width * lines.len()],
+            sym_owners: vec![None; width * lines.len()],
+            rules,
+            ..Default::default()
+        };
 
-                if !in_num {
-                    in_num = true;
-                    curr_part.row = line_no as u16;
-                    curr_part.start_col = col_no as u16;
-                    curr_part.lower_bound = if line_no == 0 {
-                        0 as u16
-                    } else {
-                        (line_no - 1) as u16
-                    };
-                    curr_part.top_bound = (line_no + 1) as u16;
-                    curr_part.left_bound = if col_no == 0 {
-                        0 as u16
-                    } else {
-                        col_no as u16 - 1
-                    };
+        let mut curr_num = String::new();
+        let mut in_num = false;
+        let mut curr_part = PartNumber::default();
+
+        for (line_no, line) in lines.iter().enumerate() {
+            for (col_no, our_char) in line.iter().copied().enumerate() {
+                schematic.cells[line_no * width + col_no] = our_char;
+
+                if our_char.is_ascii_digit() {
+                    curr_num.push(our_char);
+                    schematic.owners[line_no * width + col_no] = Some(schematic.nums.len());
+
+                    if !in_num {
+                        in_num = true;
+                        curr_part.row = line_no;
+                        curr_part.start_col = col_no;
+                        curr_part.lower_bound = line_no.saturating_sub(1);
+                        curr_part.top_bound = line_no + 1;
+                        curr_part.left_bound = col_no.saturating_sub(1);
+                    }
+                    continue;
                 }
-                continue;
+
+                // It's either a symbol or a '.'. Either way if we were in the middle of a number, we're not now!
+                if in_num {
+                    curr_part.right_bound = col_no;
+                    curr_part.end_col = col_no - 1;
+                    curr_part.number = to_part_number(&curr_num, &curr_part)?;
+                    schematic.nums.push(curr_part);
+
+                    curr_num.clear();
+                    curr_part = PartNumber::default();
+                    in_num = false;
+                }
+
+                if our_char == '.' {
+                    continue;
+                }
+
+                schematic.sym_owners[line_no * width + col_no] = Some(schematic.syms.len());
+                schematic.syms.push(Symbol {
+                    x: col_no,
+                    y: line_no,
+                    val: our_char,
+                });
             }
 
-            // It's either a symbol or a '.'. Either way if we were in the middle of a number, we're not now!
+            // We hit the end of the line, literally, and still in the middle of a number. Finish it off.
             if in_num {
-                curr_part.right_bound = col_no as u16;
-                curr_part.end_col = (col_no - 1) as u16;
-                curr_part.number = curr_num.parse::<u16>().unwrap();
-                nums.push(curr_part);
+                curr_part.right_bound = line.len();
+                curr_part.end_col = line.len() - 1;
+                curr_part.number = to_part_number(&curr_num, &curr_part)?;
+                schematic.nums.push(curr_part);
 
                 curr_num.clear();
                 curr_part = PartNumber::default();
                 in_num = false;
             }
+        }
+
+        Ok(schematic)
+    }
+
+    fn is_part_symbol(&self, x: usize, y: usize) -> bool {
+        self.rules.is_part_symbol(self.cells[y * self.width + x])
+    }
+
+    fn is_adjacent(&self, (x, y): (usize, usize), (nx, ny): (usize, usize)) -> bool {
+        match self.rules.adjacency {
+            Adjacency::Eight => true,
+            Adjacency::Four => x == nx || y == ny,
+        }
+    }
+
+    /// Every cell around (x, y), not counting (x, y) itself, that's actually on the schematic.
+    fn neighbours(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (y.saturating_sub(1)..=(y + 1).min(self.height - 1)).flat_map(move |ny| {
+            (x.saturating_sub(1)..=(x + 1).min(self.width - 1))
+                .filter(move |nx| (*nx, ny) != (x, y) && self.is_adjacent((x, y), (*nx, ny)))
+                .map(move |nx| (nx, ny))
+        })
+    }
+
+    /// Each number touching the symbol, once, however many of its digits touch.
+    fn numbers_next_to(&self, sym: &Symbol) -> Vec<&PartNumber> {
+        let mut owners: Vec<usize> = self
+            .neighbours(sym.x, sym.y)
+            .filter_map(|(x, y)| self.owners[y * self.width + x])
+            .collect();
+        owners.sort();
+        owners.dedup();
+
+        owners.into_iter().map(|idx| &self.nums[idx]).collect()
+    }
+
+    fn is_part(&self, num: &PartNumber) -> bool {
+        let right = num.right_bound.min(self.width - 1);
+        let top = num.top_bound.min(self.height - 1);
+        let (row, start, end) = (num.row, num.start_col, num.end_col);
+
+        (num.lower_bound..=top).any(|y| {
+            (num.left_bound..=right)
+                // Corners of the bounds only touch the number diagonally
+                .filter(|x| {
+                    y == row
+                        || (start..=end).contains(x)
+                        || self.rules.adjacency == Adjacency::Eight
+                })
+                .any(|x| self.is_part_symbol(x, y))
+        })
+    }
+
+    /// What `sym` is worth under `rule`, if it's a gear by that rule at all.
+    fn gear_value(&self, sym: &Symbol, rule: &GearRule) -> Option<Result<u128, SchematicError>> {
+        if sym.val != rule.symbol {
+            return None;
+        }
+
+        let nums = self.numbers_next_to(sym);
+        if nums.len() != rule.neighbours {
+            return None;
+        }
+
+        Some(
+            rule.aggregate
+                .apply(&nums)
+                .ok_or_else(|| gear_too_large(sym)),
+        )
+    }
+
+    /// What `num` adds to the part total.
+    fn part_value(&self, num: &PartNumber) -> u128 {
+        if self.is_part(num) {
+            num.number as u128
+        } else {
+            0
+        }
+    }
+
+    /// Every gear rule `sym` satisfies, along with what it's worth under each.
+    fn gear_values(&self, sym: &Symbol) -> Vec<Result<u128, SchematicError>> {
+        self.rules
+            .gears
+            .iter()
+            .filter_map(|rule| self.gear_value(sym, rule))
+            .collect()
+    }
+
+    /// What `sym` adds to the gear total, over every rule it satisfies.
+    fn gear_total(&self, sym: &Symbol) -> Result<u128, SchematicError> {
+        self.gear_values(sym)
+            .into_iter()
+            .try_fold(0_u128, |acc, value| {
+                acc.checked_add(value?).ok_or_else(|| gear_too_large(sym))
+            })
+    }
 
-            if our_char == '.' {
+    fn part_sum(&self) -> Result<u128, SchematicError> {
+        checked_sum(
+            self.nums.iter().map(|n| Ok(self.part_value(n))),
+            "part total",
+        )
+    }
+
+    fn gear_sum(&self) -> Result<u128, SchematicError> {
+        checked_sum(self.syms.iter().map(|s| self.gear_total(s)), "gear total")
+    }
+
+    /// Back to text, one line per row, e.g. to reparse from scratch.
+    fn lines(&self) -> Vec<String> {
+        self.cells
+            .chunks(self.width.max(1))
+            .map(|row| row.iter().collect())
+            .collect()
+    }
+
+    /*
+      The schematic with everything we decided coloured in, followed by a
+      plain-text legend listing the same things so it still makes sense
+      without colours:
+        - green digits are part numbers we counted
+        - dim digits are numbers we ignored
+        - each gear and its numbers share a colour (cycling through a few)
+        - red gear symbols touch the wrong number of numbers to be gears
+    */
+    fn render(&self) -> String {
+        const GEAR_COLOURS: [&str; 3] = [ansi::MAGENTA, ansi::CYAN, ansi::BLUE];
+
+        let mut colours: Vec<Option<&str>> = vec![None; self.cells.len()];
+        let mut ignored: Vec<&PartNumber> = Vec::new();
+        let mut gears: Vec<String> = Vec::new();
+        let mut not_gears: Vec<String> = Vec::new();
+
+        let cell_of = |x: usize, y: usize| y * self.width + x;
+
+        for num in &self.nums {
+            let colour = if self.is_part(num) {
+                ansi::GREEN
+            } else {
+                ignored.push(num);
+                ansi::DIM
+            };
+            for x in num.start_col..=num.end_col {
+                colours[cell_of(x, num.row)] = Some(colour);
+            }
+        }
+
+        for sym in &self.syms {
+            if !self.rules.is_gear_symbol(sym.val) {
+                colours[cell_of(sym.x, sym.y)] = Some(ansi::BOLD);
                 continue;
             }
 
-            syms.push(Symbol {
-                x: col_no as u16,
-                y: line_no as u16,
-                val: our_char.clone(),
-            });
+            let nums = self.numbers_next_to(sym);
+            let values = self.gear_values(sym);
+            if values.is_empty() {
+                colours[cell_of(sym.x, sym.y)] = Some(ansi::RED);
+                not_gears.push(format!(
+                    "{} {} touches {} number(s)",
+                    sym.val,
+                    sym,
+                    nums.len()
+                ));
+                continue;
+            }
+
+            let colour = GEAR_COLOURS[gears.len() % GEAR_COLOURS.len()];
+            colours[cell_of(sym.x, sym.y)] = Some(colour);
+            for num in &nums {
+                for x in num.start_col..=num.end_col {
+                    colours[cell_of(x, num.row)] = Some(colour);
+                }
+            }
+
+            let nums: Vec<String> = nums.iter().map(|n| n.to_string()).collect();
+            gears.push(format!(
+                "{} {} links {} for {}",
+                sym.val,
+                sym,
+                nums.join(" and "),
+                self.gear_total(sym)
+                    .map_or("more than a u128 holds".to_string(), |v| v.to_string())
+            ));
         }
 
-        // We hit the end of the line, literally, and still in the middle of a number. Finish it off.
-        if in_num {
-            curr_part.right_bound = line.len() as u16;
-            curr_part.number = curr_num.parse::<u16>().unwrap();
-            nums.push(curr_part);
+        let mut out = String::new();
+        for y in 0..self.height {
+            // Only switch colours when we have to, runs of the same colour share one escape code
+            let mut curr_colour: Option<&str> = None;
+            for x in 0..self.width {
+                let colour = colours[y * self.width + x];
+                if colour != curr_colour {
+                    out.push_str(ansi::RESET);
+                    out.push_str(colour.unwrap_or(""));
+                    curr_colour = colour;
+                }
+                out.push(self.cells[y * self.width + x]);
+            }
+            out.push_str(ansi::RESET);
+            out.push('\n');
+        }
 
-            curr_num.clear();
-            curr_part = PartNumber::default();
-            in_num = false;
+        out.push_str(
+            "\nLegend: green = counted part number, dim = ignored number, red = gear symbol that isn't a gear,\n",
+        );
+        out.push_str("        a gear and its numbers share a colour, other symbols are bold\n");
+        out.push_str(&format!("\nIgnored numbers ({}):\n", ignored.len()));
+        for num in ignored {
+            out.push_str(&format!("  {}\n", num));
+        }
+        out.push_str(&format!("\nGears ({}):\n", gears.len()));
+        for gear in gears {
+            out.push_str(&format!("  {}\n", gear));
         }
+        out.push_str(&format!(
+            "\nGear symbols that aren't gears ({}):\n",
+            not_gears.len()
+        ));
+        for not_gear in not_gears {
+            out.push_str(&format!("  {}\n", not_gear));
+        }
+
+        out
     }
+}
 
-    (nums, syms)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Totals {
+    parts: u128,
+    gears: u128,
 }
 
-fn part2() {
-    let mut our_sum: u128 = 0;
+impl fmt::Display for Totals {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "parts = {}, gears = {}", self.parts, self.gears)
+    }
+}
+
+/*
+  A schematic that can be edited a cell at a time, keeping both totals up to
+  date as it goes.
+
+  Changing one cell can only affect:
+    - numbers on its row running through it or either side of it, which can
+      appear, vanish, grow, shrink, split or join up
+    - numbers whose bounds cover it, which might gain or lose a symbol
+    - symbols next to any of the first lot, and the cell itself
+  so those get recomputed and everything else keeps its cached value.
 
-    let (nums, syms) = find_numbers_and_symbols();
+  The running totals are BigUints so an edit can never overflow them part way
+  through, whether they still fit in a u128 is only checked when asked for.
+*/
+struct EditableSchematic {
+    schematic: Schematic,
+    // What each number adds to the part total, kept in step with schematic.nums
+    part_values: Vec<u128>,
+    // What each symbol adds to the gear total, by position
+    gear_values: HashMap<(usize, usize), Result<u128, SchematicError>>,
+    // How many of gear_values are too big to add up
+    too_large: usize,
+    parts: BigUint,
+    gears: BigUint,
+}
+
+impl EditableSchematic {
+    fn new(schematic: Schematic) -> Self {
+        let part_values: Vec<u128> = schematic
+            .nums
+            .iter()
+            .map(|n| schematic.part_value(n))
+            .collect();
 
-    for sym in syms.iter().filter(|s| s.val == '*') {
-        our_sum += sym.get_gear_ratio(&nums);
+        let mut editable = EditableSchematic {
+            parts: part_values.iter().copied().map(BigUint::from).sum(),
+            schematic,
+            part_values,
+            gear_values: HashMap::new(),
+            too_large: 0,
+            gears: BigUint::default(),
+        };
+        for idx in 0..editable.schematic.syms.len() {
+            let sym = &editable.schematic.syms[idx];
+            let (pos, value) = ((sym.x, sym.y), editable.schematic.gear_total(sym));
+            editable.put_gear(pos, value);
+        }
+        editable
     }
 
+    /// Both totals, or which one has grown too big for a u128.
+    fn totals(&self) -> Result<Totals, SchematicError> {
+        if self.too_large > 0 {
+            if let Some(Err(e)) = self.gear_values.values().find(|v| v.is_err()) {
+                return Err(e.clone());
+            }
+        }
+
+        let fits = |total: &BigUint, what: &str| {
+            u128::try_from(total).map_err(|_| SchematicError::TotalTooLarge {
+                what: what.to_string(),
+            })
+        };
+        Ok(Totals {
+            parts: fits(&self.parts, "part total")?,
+            gears: fits(&self.gears, "gear total")?,
+        })
+    }
+
+    fn put_gear(&mut self, pos: (usize, usize), value: Result<u128, SchematicError>) {
+        self.take_gear(pos);
+        match value {
+            Ok(v) => self.gears += v,
+            Err(_) => self.too_large += 1,
+        }
+        self.gear_values.insert(pos, value);
+    }
+
+    fn take_gear(&mut self, pos: (usize, usize)) {
+        match self.gear_values.remove(&pos) {
+            Some(Ok(v)) => self.gears -= v,
+            Some(Err(_)) => self.too_large -= 1,
+            None => {}
+        }
+    }
+
+    /*
+      Put `ch` at (x, y). If that would make a number too big, or (x, y) is off
+      the schematic, nothing changes. Otherwise the edit is made even if the
+      totals no longer fit, `totals` will say so.
+    */
+    fn set(&mut self, x: usize, y: usize, ch: char) -> Result<(), SchematicError> {
+        let width = self.schematic.width;
+        if x >= width || y >= self.schematic.height {
+            return Err(SchematicError::OffTheSchematic { x, y });
+        }
+
+        let cell = y * width + x;
+        if self.schematic.cells[cell] == ch {
+            return Ok(());
+        }
+        if ch.is_ascii_digit() {
+            self.check_number_through(x, y, ch)?;
+        }
+
+        // Take out everything the edit could change...
+        let mut reshaped = self.row_numbers_around(x, y);
+        let mut syms = self.symbols_around(&reshaped);
+        syms.push((x, y));
+
+        for idx in self.numbers_around(x, y) {
+            self.parts -= self.part_values[idx];
+        }
+        for pos in &syms {
+            self.take_gear(*pos);
+        }
+
+        // Largest first so swap_remove never moves one we've still to remove
+        reshaped.sort_unstable_by(|a, b| b.cmp(a));
+        for idx in reshaped {
+            self.remove_number(idx);
+        }
+        if let Some(idx) = self.schematic.sym_owners[cell] {
+            self.remove_symbol(idx);
+        }
+
+        // ...make the edit...
+        self.schematic.cells[cell] = ch;
+        if !ch.is_ascii_digit() && ch != '.' {
+            self.schematic.sym_owners[cell] = Some(self.schematic.syms.len());
+            self.schematic.syms.push(Symbol { x, y, val: ch });
+        }
+        // Any digits next to the edit without an owner are new (or reshaped) numbers
+        for nx in x.saturating_sub(1)..=(x + 1).min(width - 1) {
+            let cell = y * width + nx;
+            if self.schematic.cells[cell].is_ascii_digit() && self.schematic.owners[cell].is_none()
+            {
+                self.add_number(nx, y);
+            }
+        }
+
+        // ...and put back everything it could have changed
+        syms.extend(self.symbols_around(&self.row_numbers_around(x, y)));
+        syms.sort_unstable();
+        syms.dedup();
+
+        for idx in self.numbers_around(x, y) {
+            let value = self.schematic.part_value(&self.schematic.nums[idx]);
+            self.part_values[idx] = value;
+            self.parts += value;
+        }
+        for (sx, sy) in syms {
+            if let Some(idx) = self.schematic.sym_owners[sy * width + sx] {
+                let value = self.schematic.gear_total(&self.schematic.syms[idx]);
+                // Symbols only the new numbers reach weren't taken out above, put_gear swaps them
+                self.put_gear((sx, sy), value);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Would putting digit `ch` at (x, y) make a number that doesn't fit?
+    fn check_number_through(&self, x: usize, y: usize, ch: char) -> Result<(), SchematicError> {
+        let row = &self.schematic.cells[y * self.schematic.width..(y + 1) * self.schematic.width];
+        let start = row[..x]
+            .iter()
+            .rposition(|c| !c.is_ascii_digit())
+            .map_or(0, |i| i + 1);
+        let end = row[x + 1..]
+            .iter()
+            .position(|c| !c.is_ascii_digit())
+            .map_or(row.len(), |i| x + 1 + i);
+
+        let digits: String = row[start..x]
+            .iter()
+            .chain(std::iter::once(&ch))
+            .chain(&row[x + 1..end])
+            .collect();
+        let part = PartNumber {
+            row: y,
+            start_col: start,
+            ..Default::default()
+        };
+        to_part_number(&digits, &part).map(|_| ())
+    }
+
+    /// Every number with a digit in the 3x3 around (x, y), i.e. whose bounds cover it.
+    fn numbers_around(&self, x: usize, y: usize) -> Vec<usize> {
+        let schematic = &self.schematic;
+        let mut owners: Vec<usize> = (y.saturating_sub(1)..=(y + 1).min(schematic.height - 1))
+            .flat_map(|ny| {
+                (x.saturating_sub(1)..=(x + 1).min(schematic.width - 1))
+                    .filter_map(move |nx| schematic.owners[ny * schematic.width + nx])
+            })
+            .collect();
+        owners.sort_unstable();
+        owners.dedup();
+        owners
+    }
+
+    /// The numbers on row y running through (x, y) or either side of it.
+    fn row_numbers_around(&self, x: usize, y: usize) -> Vec<usize> {
+        let width = self.schematic.width;
+        let mut owners: Vec<usize> = (x.saturating_sub(1)..=(x + 1).min(width - 1))
+            .filter_map(|nx| self.schematic.owners[y * width + nx])
+            .collect();
+        owners.dedup();
+        owners
+    }
+
+    /// Where every symbol inside the bounds of `nums` is.
+    fn symbols_around(&self, nums: &[usize]) -> Vec<(usize, usize)> {
+        let schematic = &self.schematic;
+        nums.iter()
+            .map(|&idx| &schematic.nums[idx])
+            .flat_map(|num| {
+                let right = num.right_bound.min(schematic.width - 1);
+                let top = num.top_bound.min(schematic.height - 1);
+                (num.lower_bound..=top)
+                    .flat_map(move |y| (num.left_bound..=right).map(move |x| (x, y)))
+            })
+            .filter(|&(x, y)| schematic.sym_owners[y * schematic.width + x].is_some())
+            .collect()
+    }
+
+    fn remove_number(&mut self, idx: usize) {
+        let schematic = &mut self.schematic;
+        let width = schematic.width;
+
+        let num = schematic.nums.swap_remove(idx);
+        self.part_values.swap_remove(idx);
+        for x in num.start_col..=num.end_col {
+            schematic.owners[num.row * width + x] = None;
+        }
+
+        // Whatever was last now lives at idx
+        if let Some(moved) = schematic.nums.get(idx) {
+            for x in moved.start_col..=moved.end_col {
+                schematic.owners[moved.row * width + x] = Some(idx);
+            }
+        }
+    }
+
+    fn remove_symbol(&mut self, idx: usize) {
+        let schematic = &mut self.schematic;
+        let width = schematic.width;
+
+        let sym = schematic.syms.swap_remove(idx);
+        schematic.sym_owners[sym.y * width + sym.x] = None;
+        if let Some(moved) = schematic.syms.get(idx) {
+            schematic.sym_owners[moved.y * width + moved.x] = Some(idx);
+        }
+    }
+
+    /// Adds the number whose digits run through (x, y). Its part value is filled in later.
+    fn add_number(&mut self, x: usize, y: usize) {
+        let schematic = &mut self.schematic;
+        let width = schematic.width;
+        let row = &schematic.cells[y * width..(y + 1) * width];
+
+        let start_col = row[..x]
+            .iter()
+            .rposition(|c| !c.is_ascii_digit())
+            .map_or(0, |i| i + 1);
+        let end_col = row[x..]
+            .iter()
+            .position(|c| !c.is_ascii_digit())
+            .map_or(width - 1, |i| x + i - 1);
+        let digits: String = row[start_col..=end_col].iter().collect();
+
+        let mut part = PartNumber {
+            row: y,
+            start_col,
+            end_col,
+            left_bound: start_col.saturating_sub(1),
+            right_bound: end_col + 1,
+            lower_bound: y.saturating_sub(1),
+            top_bound: y + 1,
+            ..Default::default()
+        };
+        part.number =
+            to_part_number(&digits, &part).expect("Number size was checked before the edit!");
+
+        let idx = schematic.nums.len();
+        for x in start_col..=end_col {
+            schematic.owners[y * width + x] = Some(idx);
+        }
+        schematic.nums.push(part);
+        self.part_values.push(0);
+    }
+}
+
+impl fmt::Display for EditableSchematic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.totals() {
+            Ok(totals) => write!(f, "{}", totals),
+            Err(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/*
+  Makes each edit ("x,y,ch") in turn, printing the totals as they change, then
+  checks they agree with reparsing the edited schematic from scratch.
+*/
+fn edit(schematic: Schematic, edits: &str) {
+    let rules = schematic.rules.clone();
+    let mut editable = EditableSchematic::new(schematic);
+    println!("Before: {}", editable);
+
+    for edit in edits.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+        let parts: Vec<&str> = edit.split(',').collect();
+        let (x, y, ch) = match parts[..] {
+            [x, y, ch] if ch.chars().count() == 1 => (
+                x.trim().parse::<usize>().expect("Bad x in edit"),
+                y.trim().parse::<usize>().expect("Bad y in edit"),
+                ch.chars().next().unwrap(),
+            ),
+            _ => panic!("Edits look like x,y,ch not {:?}", edit),
+        };
+
+        match editable.set(x, y, ch) {
+            Ok(()) => println!("Set ({}, {}) to {:?}: {}", x, y, ch, editable),
+            Err(e) => println!("Couldn't set ({}, {}) to {:?}: {}", x, y, ch, e),
+        }
+    }
+
+    let reparsed = Schematic::parse(editable.schematic.lines().into_iter(), rules)
+        .unwrap_or_else(|e| panic!("{}", e));
+    let reparsed = EditableSchematic::new(reparsed);
+    assert!(
+        editable.parts == reparsed.parts
+            && editable.gears == reparsed.gears
+            && editable.too_large == reparsed.too_large,
+        "Edits went wrong somewhere, reparsing gives parts = {}, gears = {}",
+        reparsed.parts,
+        reparsed.gears
+    );
+    println!("After: {} (same as reparsing)", editable);
+}
+
+fn part2(schematic: &Schematic) {
+    let our_sum = schematic.gear_sum().unwrap_or_else(|e| panic!("{}", e));
+
     // 81296995
     done!(DAY, 2, our_sum);
 }
 
-fn part1() {
-    let mut our_sum: u128 = 0;
+fn part1(schematic: &Schematic) {
+    let our_sum = schematic.part_sum().unwrap_or_else(|e| panic!("{}", e));
+    done!(DAY, 1, our_sum);
+}
+
+fn gear_too_large(sym: &Symbol) -> SchematicError {
+    SchematicError::TotalTooLarge {
+        what: format!("gear {} at {}", sym.val, sym),
+    }
+}
+
+/// Adds up `values`, stopping at the first that's too big or once the sum doesn't fit.
+fn checked_sum(
+    mut values: impl Iterator<Item = Result<u128, SchematicError>>,
+    what: &str,
+) -> Result<u128, SchematicError> {
+    values.try_fold(0_u128, |acc, value| {
+        acc.checked_add(value?)
+            .ok_or_else(|| SchematicError::TotalTooLarge {
+                what: what.to_string(),
+            })
+    })
+}
+
+fn to_part_number(digits: &str, part: &PartNumber) -> Result<u64, SchematicError> {
+    digits
+        .parse::<u64>()
+        .map_err(|_| SchematicError::NumberTooLarge {
+            row: part.row,
+            col: part.start_col,
+            digits: digits.to_string(),
+        })
+}
+
+/*
+  A made up schematic, a random mix of dots, symbols and numbers of up to
+  `max_digits` digits. It's a simple LCG so the same seed always gives the same
+  schematic.
+*/
+fn stress_schematic(width: usize, height: usize, max_digits: usize, seed: u64) -> Vec<String> {
+    let mut state = seed;
+    let mut next = move |below: u64| {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (state >> 33) % below
+    };
+
+    (0..height)
+        .map(|_| {
+            let mut line = String::with_capacity(width);
+            while line.len() < width {
+                match next(10) {
+                    0..=5 => line.push('.'),
+                    6 => line.push(['*', '#', '+', '$'][next(4) as usize]),
+                    _ => {
+                        let digits = (1 + next(max_digits as u64) as usize).min(width - line.len());
+                        line.push(char::from(b'1' + next(9) as u8));
+                        for _ in 1..digits {
+                            line.push(char::from(b'0' + next(10) as u8));
+                        }
+                        // Keep numbers apart so they don't run into each other
+                        if line.len() < width {
+                            line.push('.');
+                        }
+                    }
+                }
+            }
+            line
+        })
+        .collect()
+}
+
+fn stress(size: &str, rules: Rules) {
+    let (width, height) = size
+        .split_once('x')
+        .and_then(|(w, h)| Some((w.parse::<usize>().ok()?, h.parse::<usize>().ok()?)))
+        .expect("--stress wants WIDTHxHEIGHT, e.g. 70000x20");
+
+    let lines = stress_schematic(width, height, 19, 2023);
+
+    let start = Instant::now();
+    let schematic =
+        Schematic::parse(lines.into_iter(), rules.clone()).unwrap_or_else(|e| panic!("{}", e));
+    println!(
+        "Parsed {}x{} ({} numbers, {} symbols) in {:?}",
+        width,
+        height,
+        schematic.nums.len(),
+        schematic.syms.len(),
+        start.elapsed()
+    );
+
+    // With up to 19 digit numbers the gears can easily outgrow a u128, which is worth knowing too
+    let start = Instant::now();
+    match schematic.part_sum() {
+        Ok(parts) => println!("Part numbers sum to {} in {:?}", parts, start.elapsed()),
+        Err(e) => println!("{} (found in {:?})", e, start.elapsed()),
+    }
+
+    let start = Instant::now();
+    match schematic.gear_sum() {
+        Ok(gears) => println!("Gears sum to {} in {:?}", gears, start.elapsed()),
+        Err(e) => println!("{} (found in {:?})", e, start.elapsed()),
+    }
+
+    // One digit past what a u64 can hold
+    let too_big = vec![format!("..{}*", "9".repeat(20))];
+    match Schematic::parse(too_big.into_iter(), rules) {
+        Ok(_) => println!("A 20 digit number parsed?!"),
+        Err(e) => println!("And a 20 digit number is refused: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let (nums, syms) = find_numbers_and_symbols();
+    fn parse(lines: &[String], rules: &str) -> Result<Schematic, SchematicError> {
+        Schematic::parse(lines.iter().cloned(), rules.parse().unwrap())
+    }
+
+    /// Part 1 the slow obvious way: every run of digits with a symbol anywhere around it.
+    fn naive_part_sum(lines: &[String]) -> u128 {
+        let grid: Vec<&[u8]> = lines.iter().map(|l| l.as_bytes()).collect();
+        let is_symbol = |x: usize, y: usize| {
+            grid.get(y)
+                .and_then(|row| row.get(x))
+                .is_some_and(|c| *c != b'.' && !c.is_ascii_digit())
+        };
 
-    for num in nums {
-        if syms.iter().any(|s| num.is_adjacent_to(s)) {
-            our_sum += num.number as u128;
+        let mut sum = 0;
+        for (y, row) in grid.iter().enumerate() {
+            let mut x = 0;
+            while x < row.len() {
+                if !row[x].is_ascii_digit() {
+                    x += 1;
+                    continue;
+                }
+                let start = x;
+                while x < row.len() && row[x].is_ascii_digit() {
+                    x += 1;
+                }
+                let touches = (y.saturating_sub(1)..=y + 1)
+                    .any(|ny| (start.saturating_sub(1)..=x).any(|nx| is_symbol(nx, ny)));
+                if touches {
+                    sum += std::str::from_utf8(&row[start..x])
+                        .unwrap()
+                        .parse::<u128>()
+                        .unwrap();
+                }
+            }
+        }
+        sum
+    }
+
+    #[test]
+    fn wider_than_a_u16() {
+        let width = 70_000;
+        let lines = vec![
+            format!("7{}123.45.", ".".repeat(width - 8)),
+            format!("*{}#...", ".".repeat(width - 5)),
+            format!("{}99{}", ".".repeat(width - 10), ".".repeat(8)),
+        ];
+        let schematic = parse(&lines, "").unwrap();
+
+        assert_eq!(schematic.width, width);
+        assert!(schematic
+            .syms
+            .iter()
+            .any(|s| s.x == width - 4 && s.val == '#'));
+        assert_eq!(schematic.part_sum(), Ok(7 + 123 + 45));
+    }
+
+    #[test]
+    fn generated_schematics_match_the_obvious_way() {
+        for (width, height, seed) in [(70_000, 3, 2023), (300, 300, 7)] {
+            let lines = stress_schematic(width, height, 19, seed);
+            let schematic = parse(&lines, "").unwrap();
+            assert_eq!(schematic.part_sum(), Ok(naive_part_sum(&lines)));
+        }
+    }
+
+    #[test]
+    fn twenty_digits_is_too_many() {
+        let lines = vec![format!("..{}*", "9".repeat(20))];
+        assert_eq!(
+            parse(&lines, "").err(),
+            Some(SchematicError::NumberTooLarge {
+                row: 0,
+                col: 2,
+                digits: "9".repeat(20)
+            })
+        );
+
+        // Whereas u64::MAX itself is fine
+        let lines = vec![format!("..{}*", u64::MAX)];
+        assert_eq!(parse(&lines, "").unwrap().part_sum(), Ok(u64::MAX as u128));
+    }
+
+    #[test]
+    fn overflowing_gears_are_errors() {
+        let big = u64::MAX;
+
+        // Three u64s multiplied together can't fit
+        let lines = vec![
+            format!("{}.{}", big, big),
+            format!("{}*{}", ".".repeat(20), ".".repeat(20)),
+            format!("{}{}", big, ".".repeat(21)),
+        ];
+        let schematic = parse(&lines, "gear=*:3:product").unwrap();
+        assert_eq!(
+            schematic.gear_sum(),
+            Err(SchematicError::TotalTooLarge {
+                what: "gear * at ( 20,   1)".to_string()
+            })
+        );
+        assert!(schematic.render().contains("more than a u128 holds"));
+
+        // Two only just fit, but two gears' worth of them don't
+        let lines = vec![format!("{}*{}.{}*{}", big, big, big, big)];
+        let schematic = parse(&lines, "").unwrap();
+        assert_eq!(
+            schematic.gear_sum(),
+            Err(SchematicError::TotalTooLarge {
+                what: "gear total".to_string()
+            })
+        );
+    }
+
+    const EXAMPLE: [&str; 10] = [
+        "467..114..",
+        "...*......",
+        "..35..633.",
+        "......#...",
+        "617*......",
+        ".....+.58.",
+        "..592.....",
+        "......755.",
+        "...$.*....",
+        ".664.598..",
+    ];
+
+    fn editable(rules: &str) -> EditableSchematic {
+        let lines: Vec<String> = EXAMPLE.iter().map(|l| l.to_string()).collect();
+        EditableSchematic::new(parse(&lines, rules).unwrap())
+    }
+
+    /// Makes each edit, checking the running totals against a reparse after every one.
+    fn check_edits(editable: &mut EditableSchematic, edits: &[(usize, usize, char)]) {
+        for &(x, y, ch) in edits {
+            editable.set(x, y, ch).unwrap();
+
+            let reparsed = Schematic::parse(
+                editable.schematic.lines().into_iter(),
+                editable.schematic.rules.clone(),
+            )
+            .unwrap();
+            let expected = Totals {
+                parts: reparsed.part_sum().unwrap(),
+                gears: reparsed.gear_sum().unwrap(),
+            };
+            assert_eq!(
+                editable.totals(),
+                Ok(expected),
+                "after setting ({}, {}) to {:?}:\n{}",
+                x,
+                y,
+                ch,
+                editable.schematic.lines().join("\n")
+            );
+        }
+    }
+
+    #[test]
+    fn edits_start_from_the_example() {
+        assert_eq!(
+            editable("").totals(),
+            Ok(Totals {
+                parts: 4361,
+                gears: 467835
+            })
+        );
+    }
+
+    #[test]
+    fn joining_and_splitting_numbers() {
+        let mut editable = editable("");
+        // 467 grows into 4671, then joins up with 114, then splits apart again
+        check_edits(
+            &mut editable,
+            &[(3, 0, '1'), (4, 0, '1'), (4, 0, '.'), (3, 0, '.')],
+        );
+        // 633 splits into 6 and 3, and only 6 still touches the #
+        check_edits(&mut editable, &[(7, 2, '.'), (7, 2, '3')]);
+        // 467 shrinks to 46 and then 4
+        check_edits(&mut editable, &[(2, 0, '.'), (1, 0, '.')]);
+    }
+
+    #[test]
+    fn gears_losing_and_gaining_neighbours() {
+        let mut editable = editable("");
+        // 35 goes, so the * it shared with 467 isn't a gear any more
+        check_edits(&mut editable, &[(2, 2, '.'), (3, 2, '.')]);
+        // A new number under the lone * by 617 makes it a gear
+        check_edits(&mut editable, &[(3, 5, '9')]);
+        // And a third makes it too many
+        check_edits(&mut editable, &[(2, 3, '4')]);
+    }
+
+    #[test]
+    fn replacing_symbols() {
+        let mut editable = editable("");
+        check_edits(
+            &mut editable,
+            &[
+                (3, 1, '#'),
+                (3, 1, '*'),
+                (3, 8, '.'),
+                (6, 3, '*'),
+                (5, 5, '7'),
+            ],
+        );
+    }
+
+    #[test]
+    fn four_neighbour_rules() {
+        let mut editable = editable("adjacency=4; gear=*:2:sum; gear=#:1:max");
+        check_edits(
+            &mut editable,
+            &[
+                (3, 0, '*'),
+                (4, 1, '5'),
+                (6, 2, '.'),
+                (5, 2, '#'),
+                (3, 0, '.'),
+            ],
+        );
+    }
+
+    #[test]
+    fn refused_edits_change_nothing() {
+        let mut editable = editable("");
+        let before = editable.totals();
+        assert_eq!(
+            editable.set(10, 0, '*'),
+            Err(SchematicError::OffTheSchematic { x: 10, y: 0 })
+        );
+
+        let lines = vec![format!("{}.", "9".repeat(19))];
+        let mut big = EditableSchematic::new(parse(&lines, "").unwrap());
+        assert!(matches!(
+            big.set(19, 0, '9'),
+            Err(SchematicError::NumberTooLarge { .. })
+        ));
+        assert_eq!(big.schematic.lines(), lines);
+        assert_eq!(editable.totals(), before);
+    }
+
+    #[test]
+    fn random_edits() {
+        let cells = ['.', '.', '.', '*', '#', '1', '2', '5', '9', '0'];
+        for rules in ["", "adjacency=4; gear=*:2:sum; gear=#:3:product"] {
+            let mut editable = editable(rules);
+            let mut state: u64 = 2023;
+            let mut next = |below: usize| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                (state >> 33) as usize % below
+            };
+
+            let edits: Vec<(usize, usize, char)> = (0..300)
+                .map(|_| (next(10), next(10), cells[next(cells.len())]))
+                .collect();
+            check_edits(&mut editable, &edits);
         }
     }
-    done!(DAY, 1, our_sum);
 }
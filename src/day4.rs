@@ -1,11 +1,43 @@
 const DAY: u8 = 4;
 
 pub fn main() {
-    part1();
-    part2();
+    // One pass over the input checks the cards and plays both parts, only the problems are kept
+    let mut validator = Validator::default();
+    let mut points: u128 = 0;
+    let cards = crate::utils::get_reader_for_day(DAY)
+        .lines()
+        .map(|l| l.expect("Couldn't read a card"))
+        .filter_map(|l| validator.check(&l))
+        .inspect(|c| points += c.value());
+    let (total, lost) = streaming_cascade(cards);
+
+    // Anything odd about the cards is a warning, or with `--strict` a failure
+    // Lines we can't read at all are always a failure, there's no card to score
+    let diagnostics = validator.diagnostics;
+    if !diagnostics.is_empty() {
+        let report: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+        if crate::utils::has_flag("--strict") || diagnostics.iter().any(|d| d.issue.unreadable()) {
+            panic!(
+                "{} problem(s) with the cards:\n{}",
+                diagnostics.len(),
+                report.join("\n")
+            );
+        }
+        for line in report {
+            println!("Warning: {}", line);
+        }
+    }
+
+    part1(points);
+    part2(total, lost);
 }
 
-use std::collections::{HashMap, HashSet};
+use num_bigint::BigUint;
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt,
+    io::BufRead,
+};
 
 #[derive(Debug)]
 struct Card {
@@ -14,24 +46,35 @@ struct Card {
 }
 
 impl Card {
+    /// The card number, its winning numbers and our numbers, just as they're written.
+    fn split(raw_line: &str) -> Result<(usize, Vec<u16>, Vec<u16>), CardIssue> {
+        let malformed = || CardIssue::Malformed(raw_line.to_string());
+
+        let (header, numbers) = raw_line.split_once(':').ok_or_else(malformed)?;
+        let card_num = header
+            .trim()
+            .strip_prefix("Card")
+            .and_then(|n| n.trim().parse::<usize>().ok())
+            .ok_or_else(malformed)?;
+
+        let sections: Vec<&str> = numbers.split('|').collect();
+        let [winners, ours] = sections[..] else {
+            return Err(CardIssue::Separators {
+                found: sections.len() - 1,
+            });
+        };
+
+        Ok((card_num, numbers_in(winners)?, numbers_in(ours)?))
+    }
+
     fn from_input(raw_line: String) -> Card {
-        let cut_1: Vec<&str> = raw_line.split(':').collect();
-        let card_num = cut_1
-            .first()
-            .unwrap()
-            .split(" ")
-            .last()
-            .unwrap()
-            .parse::<usize>()
-            .unwrap();
-
-        let cut_2: Vec<&str> = cut_1.last().unwrap().split("|").collect();
-
-        let winners: HashSet<_> = HashSet::from_iter(
-            crate::utils::ints::<u16>(cut_2.first().unwrap().to_string()).into_iter(),
-        );
+        let (card_num, winners, ours) = Card::split(&raw_line)
+            .unwrap_or_else(|e| panic!("Can't read card {:?}: {}", raw_line, e));
+        Card::new(card_num, &winners, &ours)
+    }
 
-        let ours: Vec<u16> = crate::utils::ints::<u16>(cut_2.last().unwrap().to_string());
+    fn new(card_num: usize, winners: &[u16], ours: &[u16]) -> Card {
+        let winners: HashSet<&u16> = HashSet::from_iter(winners);
 
         let num_matches = ours
             .iter()
@@ -56,43 +99,540 @@ impl Card {
     }
 }
 
-fn get_cards() -> Vec<Card> {
-    crate::utils::lines(DAY)
-        .map(|l| Card::from_input(l))
+fn numbers_in(section: &str) -> Result<Vec<u16>, CardIssue> {
+    section
+        .split_ascii_whitespace()
+        .map(|n| n.parse().map_err(|_| CardIssue::BadNumber(n.to_string())))
         .collect()
 }
 
-fn part1() {
-    let cards = get_cards();
-    let our_sum: u128 = cards.iter().map(|c| c.value()).sum();
+#[derive(Debug, PartialEq, Eq)]
+enum CardIssue {
+    /// Doesn't start with "Card N:"
+    Malformed(String),
+    /// There should be exactly one | between the winning numbers and ours
+    Separators {
+        found: usize,
+    },
+    /// Not a number, or too big for a u16
+    BadNumber(String),
+    DuplicateWinner(u16),
+    DuplicateOurs(u16),
+    /// Cards `from` to `to` (inclusive) should have come before this one
+    MissingCards {
+        from: usize,
+        to: usize,
+    },
+    /// This card's number isn't after the one before it
+    OutOfOrder {
+        previous: usize,
+        found: usize,
+    },
+    WinnerCount {
+        expected: usize,
+        found: usize,
+    },
+    OursCount {
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl CardIssue {
+    /// Whether the line couldn't be read as a card at all.
+    fn unreadable(&self) -> bool {
+        matches!(
+            self,
+            CardIssue::Malformed(_) | CardIssue::Separators { .. } | CardIssue::BadNumber(_)
+        )
+    }
+}
+
+impl fmt::Display for CardIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CardIssue::Malformed(line) => {
+                write!(f, "{:?} doesn't look like \"Card N: winners | ours\"", line)
+            }
+            CardIssue::Separators { found: 0 } => {
+                write!(f, "there's no | between the winning numbers and ours")
+            }
+            CardIssue::Separators { found } => {
+                write!(f, "there are {} |s where there should be one", found)
+            }
+            CardIssue::BadNumber(n) => write!(f, "{:?} isn't a number from 0 to {}", n, u16::MAX),
+            CardIssue::DuplicateWinner(n) => write!(f, "{} is a winning number more than once", n),
+            CardIssue::DuplicateOurs(n) => write!(f, "we have {} more than once", n),
+            CardIssue::MissingCards { from, to } if from == to => {
+                write!(f, "card {} is missing", from)
+            }
+            CardIssue::MissingCards { from, to } => {
+                write!(f, "cards {} to {} are missing", from, to)
+            }
+            CardIssue::OutOfOrder { previous, found } => {
+                write!(f, "card {} comes after card {}", found, previous)
+            }
+            CardIssue::WinnerCount { expected, found } => write!(
+                f,
+                "{} winning numbers, but the first card has {}",
+                found, expected
+            ),
+            CardIssue::OursCount { expected, found } => write!(
+                f,
+                "{} of our numbers, but the first card has {}",
+                found, expected
+            ),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Diagnostic {
+    line_no: usize,
+    /// None if the line was too broken to tell
+    card: Option<usize>,
+    issue: CardIssue,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.card {
+            Some(card) => write!(f, "line {} (card {}): {}", self.line_no, card, self.issue),
+            None => write!(f, "line {}: {}", self.line_no, self.issue),
+        }
+    }
+}
+
+/// Numbers that show up more than once, once each, in order.
+fn duplicates(nums: &[u16]) -> Vec<u16> {
+    let mut seen = HashSet::new();
+    let mut dupes = Vec::new();
+    for &n in nums {
+        if !seen.insert(n) && !dupes.contains(&n) {
+            dupes.push(n);
+        }
+    }
+    dupes
+}
+
+/*
+  Lines that aren't cards at all, then everything about the cards that
+  from_input happily accepts but which quietly changes the answers:
+    - the same winning number twice, or the same one of ours twice
+    - card numbers that skip some, or go backwards
+    - cards with a different amount of numbers to the first one
+
+  Checks a line at a time so it can run alongside the parts.
+*/
+#[derive(Debug, Default)]
+struct Validator {
+    line_no: usize,
+    previous: usize,
+    counts: Option<(usize, usize)>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Validator {
+    /// Notes anything wrong with the next line, and hands back its card if it could be read.
+    fn check(&mut self, line: &str) -> Option<Card> {
+        self.line_no += 1;
+        let line_no = self.line_no;
+
+        let (card, winners, ours) = match Card::split(line) {
+            Ok(split) => split,
+            Err(issue) => {
+                // Nothing else is worth checking if we can't read it
+                self.diagnostics.push(Diagnostic {
+                    line_no,
+                    card: None,
+                    issue,
+                });
+                return None;
+            }
+        };
+        let mut report = |issue| {
+            self.diagnostics.push(Diagnostic {
+                line_no,
+                card: Some(card),
+                issue,
+            })
+        };
+
+        for n in duplicates(&winners) {
+            report(CardIssue::DuplicateWinner(n));
+        }
+        for n in duplicates(&ours) {
+            report(CardIssue::DuplicateOurs(n));
+        }
+
+        let previous = self.previous;
+        if card <= previous {
+            report(CardIssue::OutOfOrder {
+                previous,
+                found: card,
+            });
+        } else {
+            if card > previous + 1 {
+                report(CardIssue::MissingCards {
+                    from: previous + 1,
+                    to: card - 1,
+                });
+            }
+            self.previous = card;
+        }
+
+        let (expected_winners, expected_ours) =
+            *self.counts.get_or_insert((winners.len(), ours.len()));
+        if winners.len() != expected_winners {
+            report(CardIssue::WinnerCount {
+                expected: expected_winners,
+                found: winners.len(),
+            });
+        }
+        if ours.len() != expected_ours {
+            report(CardIssue::OursCount {
+                expected: expected_ours,
+                found: ours.len(),
+            });
+        }
+
+        Some(Card::new(card, &winners, &ours))
+    }
+}
+
+fn part1(points: u128) {
+    done!(DAY, 1, points);
+}
+
+/// Card `card` has more matches than there are cards after it.
+#[derive(Debug)]
+struct Overrun {
+    card: usize,
+    num_matches: usize,
+    cards_after: usize,
+}
+
+impl fmt::Display for Overrun {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Card {} has {} matches but only {} card(s) come after it, so {} of them win nothing",
+            self.card,
+            self.num_matches,
+            self.cards_after,
+            self.num_matches - self.cards_after
+        )
+    }
+}
+
+/*
+  Where every copy of every card came from, in input order. Each card starts
+  with its original, then `received[idx]` says how many copies each earlier
+  card (by position) handed it. Cards win the ones after them in the input
+  whatever they're numbered, same as streaming_cascade.
+*/
+#[derive(Debug, Default)]
+struct Trace {
+    nums: Vec<usize>,
+    received: Vec<Vec<(usize, BigUint)>>,
+    overruns: Vec<Overrun>,
+}
+
+impl Trace {
+    fn copies_of(&self, idx: usize) -> BigUint {
+        self.received[idx]
+            .iter()
+            .fold(BigUint::from(1_u32), |acc, (_, n)| acc + n)
+    }
+
+    fn total(&self) -> BigUint {
+        (0..self.nums.len()).map(|idx| self.copies_of(idx)).sum()
+    }
+
+    fn table(&self) -> String {
+        let mut out = format!("{:>5} | {:>10} | From\n", "Card", "Copies");
+        for (idx, from) in self.received.iter().enumerate() {
+            let from: Vec<String> = from
+                .iter()
+                .map(|(c, n)| format!("{} x{}", self.nums[*c], n))
+                .collect();
+            out.push_str(&format!(
+                "{:>5} | {:>10} | original{}{}\n",
+                self.nums[idx],
+                self.copies_of(idx),
+                if from.is_empty() { "" } else { ", " },
+                from.join(", ")
+            ));
+        }
+        out
+    }
 
-    done!(DAY, 1, our_sum);
+    /// For Graphviz, e.g. `cargo run -- 4 --trace-dot | dot -Tsvg > day4.svg`
+    fn dot(&self) -> String {
+        // Nodes go by position so repeated card numbers still get one each
+        let mut out = String::from("digraph cascade {\n    rankdir=LR;\n");
+        for (idx, card) in self.nums.iter().enumerate() {
+            let style = if self.overruns.iter().any(|o| o.card == *card) {
+                ", color=red"
+            } else {
+                ""
+            };
+            out.push_str(&format!(
+                "    card{} [label=\"Card {}\\n{} copies\"{}];\n",
+                idx,
+                card,
+                self.copies_of(idx),
+                style
+            ));
+        }
+        for (idx, from) in self.received.iter().enumerate() {
+            for (from_idx, copies) in from {
+                out.push_str(&format!(
+                    "    card{} -> card{} [label=\"{}\"];\n",
+                    from_idx, idx, copies
+                ));
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
 }
 
-fn part2() {
-    let cards = get_cards();
+/*
+  Plays out the whole cascade, noting who won what. Winning copies of cards
+  past the last one don't exist, so those are dropped and flagged instead.
+*/
+fn cascade(cards: &[Card]) -> Trace {
+    let mut trace = Trace {
+        nums: cards.iter().map(|c| c.num).collect(),
+        received: vec![Vec::new(); cards.len()],
+        ..Default::default()
+    };
 
-    // card number and how many copies we have
-    let mut num_copies: HashMap<usize, usize> = HashMap::new();
-    let mut copies_to_add;
+    for (idx, curr_card) in cards.iter().enumerate() {
+        // The original plus whatever earlier cards won
+        let copies = trace.copies_of(idx);
 
-    for curr_card in cards.iter() {
-        // Put the original in the stack. There may have already been some.
-        *num_copies.entry(curr_card.num).or_insert(0) += 1;
-        copies_to_add = num_copies.get(&curr_card.num).cloned().unwrap();
+        let cards_after = cards.len() - idx - 1;
+        if curr_card.num_matches > cards_after {
+            trace.overruns.push(Overrun {
+                card: curr_card.num,
+                num_matches: curr_card.num_matches,
+                cards_after,
+            });
+        }
 
-        if curr_card.num_matches == 0 {
-            // No matches, so we don't get any additional copies
-            continue;
+        for won in idx + 1..=idx + curr_card.num_matches.min(cards_after) {
+            trace.received[won].push((idx, copies.clone()));
         }
+    }
+
+    trace
+}
+
+/*
+  Same answer as cascade, but one card at a time as they're read. All we
+  hold on to is how many copies the next few cards have already won, which is
+  never more than the most matches any card has. Copies can double every card,
+  so they're counted in BigUints.
 
-        // We have at least one copy of the current card, but possibly more.
-        // To avoid nested loops, just add the current number of copies as the
-        // number of copies for each of the next cards.
-        for offset in 1..=curr_card.num_matches {
-            *num_copies.entry(curr_card.num + offset).or_insert(0) += copies_to_add;
+  Also hands back how many copies were won of cards past the last one.
+*/
+fn streaming_cascade(cards: impl Iterator<Item = Card>) -> (BigUint, BigUint) {
+    // pending[0] is for the card we're on, pending[1] the one after, and so on
+    let mut pending: VecDeque<BigUint> = VecDeque::new();
+    let mut total = BigUint::default();
+
+    for card in cards {
+        let copies = pending.pop_front().unwrap_or_default() + 1_u32;
+
+        if pending.len() < card.num_matches {
+            pending.resize(card.num_matches, BigUint::default());
+        }
+        for won in pending.iter_mut().take(card.num_matches) {
+            *won += &copies;
         }
+
+        total += copies;
+    }
+
+    (total, pending.into_iter().sum())
+}
+
+fn part2(total: BigUint, lost: BigUint) {
+    if lost > BigUint::default() {
+        println!(
+            "{} copies were won of cards past the last one and don't count (--trace for which)",
+            lost
+        );
+    }
+
+    // e.g. `cargo run -- 4 --trace` for a table of where each card's copies came from
+    if crate::utils::has_flag("--trace") || crate::utils::has_flag("--trace-dot") {
+        trace(&total);
+    }
+
+    done!(DAY, 2, total);
+}
+
+/// Holds on to every card, unlike the parts, so it's for looking into small inputs.
+fn trace(total: &BigUint) {
+    let cards: Vec<Card> = crate::utils::lines(DAY).map(Card::from_input).collect();
+    let trace = cascade(&cards);
+    assert_eq!(
+        &trace.total(),
+        total,
+        "The trace and the streamed cascade disagree!"
+    );
+    for overrun in &trace.overruns {
+        println!("{}", overrun);
+    }
+
+    if crate::utils::has_flag("--trace-dot") {
+        print!("{}", trace.dot());
+    } else {
+        print!("{}", trace.table());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: [&str; 6] = [
+        "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53",
+        "Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19",
+        "Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1",
+        "Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83",
+        "Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36",
+        "Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11",
+    ];
+
+    fn lines(cards: &[&str]) -> impl Iterator<Item = String> {
+        cards
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    /// Both cascades, checking they agree on the total.
+    fn both_cascades(cards: &[&str]) -> (Trace, BigUint, BigUint) {
+        let (total, lost) = streaming_cascade(lines(cards).map(Card::from_input));
+        let trace = cascade(&lines(cards).map(Card::from_input).collect::<Vec<_>>());
+        assert_eq!(trace.total(), total);
+        (trace, total, lost)
+    }
+
+    #[test]
+    fn cascades_agree_on_the_example() {
+        let (trace, total, lost) = both_cascades(&EXAMPLE);
+        assert_eq!(total, BigUint::from(30_u32));
+        assert_eq!(lost, BigUint::default());
+        assert!(trace.overruns.is_empty());
+        assert_eq!(trace.copies_of(3), BigUint::from(8_u32));
+    }
+
+    #[test]
+    fn cascades_agree_past_the_last_card() {
+        // Card 1 wins 3 cards but there's only 1 after it, and card 2 wins 1 with none after it
+        let (trace, total, lost) = both_cascades(&["Card 1: 1 2 3 | 1 2 3", "Card 2: 4 | 4"]);
+        assert_eq!(total, BigUint::from(3_u32));
+        assert_eq!(lost, BigUint::from(4_u32));
+        assert_eq!(
+            trace
+                .overruns
+                .iter()
+                .map(|o| (o.card, o.cards_after))
+                .collect::<Vec<_>>(),
+            vec![(1, 1), (2, 0)]
+        );
+    }
+
+    #[test]
+    fn cascades_agree_past_a_u128() {
+        // Two matches a card makes the copies grow like Fibonacci numbers
+        let cards = vec!["Card 1: 1 2 | 1 2"; 200];
+        let (_, total, _) = both_cascades(&cards);
+        assert!(total > BigUint::from(u128::MAX));
     }
 
-    done!(DAY, 2, num_copies.values().sum::<usize>());
+    #[test]
+    fn every_card_issue_with_its_line() {
+        let cards = [
+            "Card 1: 1 2 3 | 4 5 6",
+            "Card 2: 1 1 3 | 4 5 5",
+            "Card 4: 1 2 3 | 4 5 6",
+            "Card 8: 1 2 3 | 4 5 6",
+            "Card 6: 1 2 3 | 4 5 6",
+            "Card 9: 1 2 | 4 5 6",
+            "Card 10: 1 2 3 | 4 5 6 7",
+            "Card x: 1 | 2",
+            "stray text",
+            "Card 11: 1 2 3  4 5 6",
+            "Card 12: 1 | 2 | 3",
+            "Card 13: 1 2 70000 | 4 5 6",
+            "Card 14: 1 2 a | 4 5 6",
+        ];
+        let mut validator = Validator::default();
+        let read = cards.iter().filter_map(|c| validator.check(c)).count();
+        assert_eq!(read, 7);
+
+        let found: Vec<(usize, Option<usize>, CardIssue)> = validator
+            .diagnostics
+            .into_iter()
+            .map(|d| (d.line_no, d.card, d.issue))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (2, Some(2), CardIssue::DuplicateWinner(1)),
+                (2, Some(2), CardIssue::DuplicateOurs(5)),
+                (3, Some(4), CardIssue::MissingCards { from: 3, to: 3 }),
+                (4, Some(8), CardIssue::MissingCards { from: 5, to: 7 }),
+                (
+                    5,
+                    Some(6),
+                    CardIssue::OutOfOrder {
+                        previous: 8,
+                        found: 6
+                    }
+                ),
+                (
+                    6,
+                    Some(9),
+                    CardIssue::WinnerCount {
+                        expected: 3,
+                        found: 2
+                    }
+                ),
+                (
+                    7,
+                    Some(10),
+                    CardIssue::OursCount {
+                        expected: 3,
+                        found: 4
+                    }
+                ),
+                (8, None, CardIssue::Malformed("Card x: 1 | 2".to_string())),
+                (9, None, CardIssue::Malformed("stray text".to_string())),
+                (10, None, CardIssue::Separators { found: 0 }),
+                (11, None, CardIssue::Separators { found: 2 }),
+                (12, None, CardIssue::BadNumber("70000".to_string())),
+                (13, None, CardIssue::BadNumber("a".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn good_cards_have_nothing_to_say() {
+        let mut validator = Validator::default();
+        let points: u128 = EXAMPLE
+            .iter()
+            .filter_map(|c| validator.check(c))
+            .map(|c| c.value())
+            .sum();
+        assert_eq!(points, 13);
+        assert!(validator.diagnostics.is_empty());
+    }
 }
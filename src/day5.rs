@@ -1,14 +1,25 @@
 const DAY: u8 = 5;
 
 pub fn main() {
+    if let Some(route) = utils::arg_value("--route") {
+        query(&get_maps(), &route);
+        return;
+    }
+
+    // e.g. `cargo run -- 5 --chart`, or `--chart-svg > day5.svg`
+    if utils::has_flag("--chart") || utils::has_flag("--chart-svg") {
+        chart();
+        return;
+    }
+
     part1();
     part2();
 }
 
-use crate::utils;
-use std::{io::BufRead, ops::Range};
+use crate::utils::{self, IntervalSet};
+use std::{collections::HashMap, error::Error, fmt, io::BufRead, ops::Range};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct SeedMapRow {
     source_start: i128,
     source_end: i128,
@@ -16,8 +27,10 @@ struct SeedMapRow {
 }
 
 fn get_seeds() -> Vec<i128> {
-    let mut reader = utils::get_reader_for_day(5);
+    seeds_from(utils::get_reader_for_day(DAY))
+}
 
+fn seeds_from(mut reader: impl BufRead) -> Vec<i128> {
     let mut seed_line = String::new();
     reader
         .read_line(&mut seed_line)
@@ -26,122 +39,832 @@ fn get_seeds() -> Vec<i128> {
     crate::utils::ints::<i128>(seed_line.split(":").last().unwrap().to_string())
 }
 
-fn get_maps() -> Vec<Vec<SeedMapRow>> {
-    let mut all_maps: Vec<Vec<SeedMapRow>> = Vec::new();
-    let mut curr_map: Vec<SeedMapRow> = Vec::new();
+/// One "source-to-dest map:" block from the almanac.
+#[derive(Debug)]
+struct SeedMap {
+    source: String,
+    dest: String,
+    rows: Vec<SeedMapRow>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum RouteError {
+    /// Following maps from `from` got to `stuck_at`, and nothing maps from there
+    Missing {
+        from: String,
+        to: String,
+        stuck_at: String,
+    },
+    /// Following maps from `path[0]` came back around without reaching `to`
+    Cycle { to: String, path: Vec<String> },
+}
 
-    for line in crate::utils::lines(DAY) {
-        if line.is_empty() {
-            if !curr_map.is_empty() {
-                curr_map.sort_by(|a, b| a.source_start.cmp(&b.source_start));
-                all_maps.push(curr_map);
+impl fmt::Display for RouteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RouteError::Missing { from, to, stuck_at } => write!(
+                f,
+                "Can't get from {} to {}, nothing maps from {}",
+                from, to, stuck_at
+            ),
+            RouteError::Cycle { to, path } => write!(
+                f,
+                "Can't get to {}, the maps go round in circles: {}",
+                to,
+                path.join(" -> ")
+            ),
+        }
+    }
+}
+
+impl Error for RouteError {}
+
+/// Every map in the almanac, by the category it maps from.
+#[derive(Debug, Default)]
+struct Almanac {
+    maps: HashMap<String, SeedMap>,
+}
+
+impl Almanac {
+    fn add(&mut self, mut map: SeedMap) {
+        map.rows.sort_by_key(|a| a.source_start);
+        if let Some(other) = self.maps.get(&map.source) {
+            panic!(
+                "Two maps from {}, one to {} and one to {}",
+                map.source, other.dest, map.dest
+            );
+        }
+        self.maps.insert(map.source.clone(), map);
+    }
+
+    /// The maps to go through, in order, to get from category `from` to `to`.
+    fn route(&self, from: &str, to: &str) -> Result<Vec<&SeedMap>, RouteError> {
+        let mut route = Vec::new();
+        let mut seen = vec![from.to_string()];
+        let mut curr = from;
+
+        while curr != to {
+            let map = self.maps.get(curr).ok_or_else(|| RouteError::Missing {
+                from: from.to_string(),
+                to: to.to_string(),
+                stuck_at: curr.to_string(),
+            })?;
+
+            let looped = seen.contains(&map.dest);
+            seen.push(map.dest.clone());
+            if looped {
+                return Err(RouteError::Cycle {
+                    to: to.to_string(),
+                    path: seen,
+                });
+            }
+
+            route.push(map);
+            curr = &map.dest;
+        }
+
+        Ok(route)
+    }
+}
+
+fn get_maps() -> Almanac {
+    maps_from(crate::utils::lines(DAY))
+}
+
+fn maps_from(lines: impl Iterator<Item = String>) -> Almanac {
+    let mut almanac = Almanac::default();
+    let mut curr_map: Option<SeedMap> = None;
+
+    for line in lines {
+        if let Some(header) = line.strip_suffix(" map:") {
+            let (source, dest) = header
+                .split_once("-to-")
+                .unwrap_or_else(|| panic!("Map header isn't source-to-dest: {:?}", line));
+
+            let next_map = SeedMap {
+                source: source.to_string(),
+                dest: dest.to_string(),
+                rows: Vec::new(),
+            };
+            if let Some(map) = curr_map.replace(next_map) {
+                almanac.add(map);
             }
-            curr_map = Vec::new();
             continue;
         }
 
-        if line.contains("map") || line.contains("seeds") {
-            // It's a header line, for now we don't care
+        if line.is_empty() || line.starts_with("seeds") {
             continue;
         }
 
         let line_parts: Vec<i128> = crate::utils::ints(line);
 
-        let dest = *line_parts.get(0).unwrap();
+        let dest = *line_parts.first().unwrap();
         let src = *line_parts.get(1).unwrap();
         let offset = *line_parts.get(2).unwrap();
 
-        curr_map.push(SeedMapRow {
-            source_start: src,
-            source_end: src + offset,
-            delta: dest - src,
-        })
+        curr_map
+            .as_mut()
+            .expect("Mapping row before any map header?!")
+            .rows
+            .push(SeedMapRow {
+                source_start: src,
+                source_end: src + offset,
+                delta: dest - src,
+            })
     }
 
     // Add the last one
-    curr_map.sort_by(|a, b| a.source_start.cmp(&b.source_start));
-    all_maps.push(curr_map);
+    if let Some(map) = curr_map {
+        almanac.add(map);
+    }
+
+    almanac
+}
 
-    all_maps
+/*
+  A function on the numbers, made up of SeedMapRows that are sorted and don't
+  overlap. Anything that isn't in a row maps to itself, so no rows at all is
+  the identity.
+
+  Composing every layer of the almanac into one of these means a seed goes
+  straight to its location with a single binary search.
+*/
+#[derive(Debug, Default)]
+struct Piecewise {
+    rows: Vec<SeedMapRow>,
 }
 
-fn map_seeds(seeds: &mut Vec<i128>, maps: &Vec<Vec<SeedMapRow>>) {
-    for map in maps.iter() {
-        for seed in &mut *seeds {
-            // First check to see if the source is min/max out-of-bounds and thus doesn't change
-            if *seed < map.first().unwrap().source_start || *seed > map.last().unwrap().source_end {
-                continue;
+impl Piecewise {
+    fn from_map(map: &SeedMap) -> Self {
+        Piecewise {
+            rows: map.rows.clone(),
+        }
+    }
+
+    /// Every map in `maps`, one after the other.
+    fn compose_all(maps: &[&SeedMap]) -> Self {
+        maps.iter().fold(Piecewise::default(), |composed, map| {
+            composed.then(&Piecewise::from_map(map))
+        })
+    }
+
+    /// The rows plus the gaps between them (with a delta of 0), covering every i128.
+    fn segments(&self) -> Vec<SeedMapRow> {
+        let mut segments = Vec::with_capacity(self.rows.len() * 2 + 1);
+        let mut pos = i128::MIN;
+
+        for row in &self.rows {
+            if row.source_start > pos {
+                segments.push(SeedMapRow {
+                    source_start: pos,
+                    source_end: row.source_start,
+                    delta: 0,
+                });
             }
+            segments.push(row.clone());
+            pos = row.source_end;
+        }
+
+        if pos < i128::MAX {
+            segments.push(SeedMapRow {
+                source_start: pos,
+                source_end: i128::MAX,
+                delta: 0,
+            });
+        }
 
-            for row in map {
-                if *seed >= row.source_start && *seed < row.source_end {
-                    // We have something to change!
-                    *seed += row.delta;
-                    break;
+        segments
+    }
+
+    /// This, then `next` on whatever comes out.
+    fn then(&self, next: &Piecewise) -> Piecewise {
+        let mut rows: Vec<SeedMapRow> = Vec::new();
+        let mut push = |source_start: i128, source_end: i128, delta: i128| {
+            if delta == 0 || source_start >= source_end {
+                return;
+            }
+            // Neighbours that end up moving the same amount are really one row
+            if let Some(last) = rows.last_mut() {
+                if last.source_end == source_start && last.delta == delta {
+                    last.source_end = source_end;
+                    return;
                 }
             }
+            rows.push(SeedMapRow {
+                source_start,
+                source_end,
+                delta,
+            });
+        };
+
+        for seg in self.segments() {
+            let delta = seg.delta;
+            // Only the gaps reach out to i128::MIN/MAX, and they don't move anything
+            let (out_start, out_end) = (seg.source_start + delta, seg.source_end + delta);
+
+            // First of next's rows that could overlap what this segment puts out
+            let first = next.rows.partition_point(|r| r.source_end <= out_start);
+            let mut pos = seg.source_start;
+
+            for row in next.rows[first..]
+                .iter()
+                .take_while(|r| r.source_start < out_end)
+            {
+                let start = (row.source_start - delta).max(pos);
+                let end = (row.source_end - delta).min(seg.source_end);
+
+                push(pos, start, delta);
+                push(start, end, delta + row.delta);
+                pos = end;
+            }
+
+            push(pos, seg.source_end, delta);
+        }
+
+        Piecewise { rows }
+    }
+
+    fn apply(&self, x: i128) -> i128 {
+        let idx = self.rows.partition_point(|r| r.source_end <= x);
+        match self.rows.get(idx) {
+            Some(row) if row.source_start <= x => x + row.delta,
+            _ => x,
+        }
+    }
+
+    /*
+      Every number that maps into `targets`, gaps included. Rows can land on
+      top of each other, or on top of a gap, so one location can come from a
+      few places.
+    */
+    fn preimage(&self, targets: &IntervalSet<i128>) -> IntervalSet<i128> {
+        self.segments()
+            .iter()
+            .map(|seg| {
+                let out = (seg.source_start + seg.delta)..(seg.source_end + seg.delta);
+                targets
+                    .intersection(&IntervalSet::from(out.clone()))
+                    .shift(out, -seg.delta)
+            })
+            .fold(IntervalSet::new(), |all, from_seg| all.union(&from_seg))
+    }
+
+    /// The smallest thing any number in `ranges` maps to.
+    fn min_over(&self, ranges: &IntervalSet<i128>) -> Option<i128> {
+        let segments = self.segments();
+
+        ranges
+            .ranges()
+            .iter()
+            .flat_map(|range| {
+                let first = segments.partition_point(|s| s.source_end <= range.start);
+                segments[first..]
+                    .iter()
+                    .take_while(move |s| s.source_start < range.end)
+                    // Each piece only shifts, so its smallest output is from its smallest input
+                    .map(move |s| s.source_start.max(range.start) + s.delta)
+            })
+            .min()
+    }
+}
+
+impl fmt::Display for Piecewise {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{:>12} {:>12} {:>12}   {:>12} {:>12}",
+            "from", "to", "delta", "maps to", "up to"
+        )?;
+        for row in &self.rows {
+            writeln!(
+                f,
+                "{:>12} {:>12} {:>+12}   {:>12} {:>12}",
+                row.source_start,
+                row.source_end,
+                row.delta,
+                row.source_start + row.delta,
+                row.source_end + row.delta
+            )?;
         }
+        write!(
+            f,
+            "({} pieces, anything else maps to itself)",
+            self.rows.len()
+        )
+    }
+}
+
+fn describe(set: &IntervalSet<i128>) -> String {
+    if set.is_empty() {
+        return String::from("nothing");
     }
+    let ranges: Vec<String> = set.ranges().iter().map(|r| format!("{:?}", r)).collect();
+    ranges.join(", ")
 }
 
-fn get_ranges() -> Vec<Range<i128>> {
-    let mut ranges: Vec<Range<i128>> = get_seeds().chunks(2).map(|c| c[0]..c[0] + c[1]).collect();
+/*
+  Answers "what does X in one category turn into in another?", e.g.
+  `cargo run -- 5 --route soil:humidity --values 81,14`. Without --values it
+  just shows the maps it'd go through.
+
+  Going backwards works too, e.g. `--route location:seed --values 46` gives
+  every seed range that ends up at location 46.
+*/
+fn query(almanac: &Almanac, route: &str) {
+    let (from, to) = route
+        .split_once(':')
+        .expect("--route wants FROM:TO, e.g. soil:humidity");
+    if let Ok(maps) = almanac.route(from, to) {
+        let mut names = vec![from];
+        names.extend(maps.iter().map(|m| m.dest.as_str()));
+        println!("{}", names.join(" -> "));
+
+        if let Some(values) = utils::arg_value("--values") {
+            let composed = Piecewise::compose_all(&maps);
+            for value in utils::ints::<i128>(values.replace(',', " ")) {
+                println!("{} {} -> {} {}", from, value, to, composed.apply(value));
+            }
+        }
+        return;
+    }
+
+    // No maps that way, but maybe there are the other way round
+    let maps = almanac
+        .route(to, from)
+        .or_else(|_| almanac.route(from, to))
+        .unwrap_or_else(|e| panic!("{}", e));
 
-    ranges.sort_by(|a, b| a.start.cmp(&b.start));
+    let mut names = vec![from];
+    names.extend(maps.iter().rev().map(|m| m.source.as_str()));
+    println!("{}", names.join(" <- "));
 
-    ranges
+    if let Some(values) = utils::arg_value("--values") {
+        let composed = Piecewise::compose_all(&maps);
+        for value in utils::ints::<i128>(values.replace(',', " ")) {
+            let sources = composed.preimage(&IntervalSet::from(value..value + 1));
+            println!("{} {} <- {} {}", from, value, to, describe(&sources));
+        }
+    }
 }
 
-fn part1() {
-    let mut seeds = get_seeds();
-    let maps = get_maps();
+fn get_ranges() -> IntervalSet<i128> {
+    ranges_from(&get_seeds())
+}
 
-    map_seeds(&mut seeds, &maps);
+/// Part 2 reads the seeds as (start, length) pairs.
+fn ranges_from(seeds: &[i128]) -> IntervalSet<i128> {
+    seeds.chunks(2).map(|c| c[0]..c[0] + c[1]).collect()
+}
 
-    done!(DAY, 1, seeds.iter().min().unwrap());
+/// A bit of one of the seed ranges, on its way through one map.
+#[derive(Debug, Clone)]
+struct Fragment {
+    // Which of the input's seed ranges this came from
+    seed_range: usize,
+    before: Range<i128>,
+    after: Range<i128>,
+    // Which of the map's rows moved it, None if it fell through a gap
+    row: Option<usize>,
 }
 
-fn part2() {
-    let maps = get_maps();
-    let mut ranges = get_ranges();
-    let mut range: Range<i128>;
+/// Everything going into one map, and what came out.
+struct Layer<'a> {
+    map: &'a SeedMap,
+    fragments: Vec<Fragment>,
+}
+
+/// `range` cut up by which of `rows` (if any) each bit of it lands in.
+fn split(range: Range<i128>, rows: &[SeedMapRow]) -> Vec<(Range<i128>, Option<usize>)> {
+    let mut pieces = Vec::new();
+    let mut pos = range.start;
+    let first = rows.partition_point(|r| r.source_end <= range.start);
+
+    for (idx, row) in rows
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take_while(|(_, r)| r.source_start < range.end)
+    {
+        if row.source_start > pos {
+            pieces.push((pos..row.source_start, None));
+        }
+        let end = row.source_end.min(range.end);
+        pieces.push((pos.max(row.source_start)..end, Some(idx)));
+        pos = end;
+    }
+
+    if pos < range.end {
+        pieces.push((pos..range.end, None));
+    }
+
+    pieces
+}
+
+/*
+  The part 2 range-splitting done one map at a time, keeping every fragment
+  separate (even when they end up next to each other) and noting which row
+  moved it, so it can be drawn.
+*/
+fn fragment<'a>(seed_ranges: &[Range<i128>], maps: &[&'a SeedMap]) -> Vec<Layer<'a>> {
+    let mut curr: Vec<(usize, Range<i128>)> = seed_ranges.iter().cloned().enumerate().collect();
+    let mut layers = Vec::new();
 
     for map in maps {
-        let mut new_ranges: Vec<Range<i128>> = Vec::new();
-        'next_range: while !ranges.is_empty() {
-            range = ranges.pop().unwrap();
-            for row in &map {
-                let overlap_start = std::cmp::max(range.start, row.source_start);
-                let overlap_end = std::cmp::min(range.end, row.source_end);
-
-                if overlap_start < overlap_end {
-                    // We have some overlap, so let's map what we need to and
-                    // toss anything that's left over back into the ranges Vec
-                    // to try processing again
-                    new_ranges.push((overlap_start + row.delta)..(overlap_end + row.delta));
-
-                    if range.start < overlap_start {
-                        // Everything in the seed range before this mapping row gets pushed back in for further processing
-                        ranges.push(range.start..overlap_start);
-                    }
-
-                    if range.end > overlap_end {
-                        // Everything in the seed range after this mapping row gets pushed back in for further processing
-                        ranges.push(overlap_end..range.end);
-                    }
-
-                    continue 'next_range;
-                }
+        let mut fragments = Vec::new();
+        for (seed_range, range) in &curr {
+            for (before, row) in split(range.clone(), &map.rows) {
+                let delta = row.map_or(0, |idx| map.rows[idx].delta);
+                fragments.push(Fragment {
+                    seed_range: *seed_range,
+                    after: (before.start + delta)..(before.end + delta),
+                    before,
+                    row,
+                });
             }
-            // None of the mapping rows applied to this seed range, toss it in the new pile
-            new_ranges.push(range.start..range.end);
-            continue;
         }
 
-        ranges = new_ranges;
+        curr = fragments
+            .iter()
+            .map(|f| (f.seed_range, f.after.clone()))
+            .collect();
+        layers.push(Layer { map, fragments });
+    }
+
+    layers
+}
+
+/// How rows are labelled in the charts: A-Z, then a-z, then we give up.
+fn row_label(idx: usize) -> char {
+    match idx {
+        0..=25 => (b'A' + idx as u8) as char,
+        26..=51 => (b'a' + (idx - 26) as u8) as char,
+        _ => '#',
+    }
+}
+
+/// Squashes lo..hi down to 0..width.
+struct Scale {
+    lo: i128,
+    hi: i128,
+    width: f64,
+}
+
+impl Scale {
+    fn new(layers: &[Layer], seed_ranges: &[Range<i128>], width: f64) -> Self {
+        // Every fragment, and every row that caught one
+        let ends = seed_ranges
+            .iter()
+            .cloned()
+            .chain(layers.iter().flat_map(|l| {
+                l.fragments.iter().flat_map(|f| {
+                    let row = f.row.map(|idx| &l.map.rows[idx]);
+                    [f.after.clone()]
+                        .into_iter()
+                        .chain(row.map(|r| r.source_start..r.source_end))
+                })
+            }));
+        let (lo, hi) = ends.fold((i128::MAX, i128::MIN), |(lo, hi), r| {
+            (lo.min(r.start), hi.max(r.end))
+        });
+
+        Scale {
+            lo,
+            hi: hi.max(lo + 1),
+            width,
+        }
+    }
+
+    fn at(&self, x: i128) -> f64 {
+        (x - self.lo) as f64 / (self.hi - self.lo) as f64 * self.width
+    }
+
+    /// Which columns `range` covers, always at least one.
+    fn columns(&self, range: &Range<i128>) -> Range<usize> {
+        let last = self.width as usize - 1;
+        let start = (self.at(range.start) as usize).min(last);
+        let end = (self.at(range.end) as usize).clamp(start + 1, last + 1);
+        start..end
+    }
+}
+
+/*
+  One block per map, all on the same scale:
+    rows  the source of each row that moved something, by its letter
+    in    what went into the map, by the row that'll move it ('=' for a gap)
+    out   where it all ended up, same labels
+  then a key saying what each letter is.
+*/
+fn chart_ascii(layers: &[Layer], seed_ranges: &[Range<i128>]) -> String {
+    const WIDTH: usize = 100;
+    let scale = Scale::new(layers, seed_ranges, WIDTH as f64);
+
+    let bar = |marks: &mut dyn Iterator<Item = (Range<i128>, char)>| {
+        let mut line = vec![' '; WIDTH];
+        for (range, label) in marks {
+            for col in scale.columns(&range) {
+                line[col] = label;
+            }
+        }
+        format!("|{}|", line.into_iter().collect::<String>())
+    };
+
+    let mut out = format!("Everything from {} to {}\n\n", scale.lo, scale.hi);
+    out.push_str(&format!(
+        "{:>6} {}  {} range(s)\n",
+        "seeds",
+        bar(&mut seed_ranges.iter().enumerate().map(|(idx, r)| (
+            r.clone(),
+            char::from_digit(((idx + 1) % 10) as u32, 10).unwrap()
+        ))),
+        seed_ranges.len()
+    ));
+
+    for layer in layers {
+        let mut used: Vec<usize> = layer.fragments.iter().filter_map(|f| f.row).collect();
+        used.sort_unstable();
+        used.dedup();
+
+        let label = |f: &Fragment| f.row.map_or('=', row_label);
+        out.push_str(&format!("\n{}-to-{}\n", layer.map.source, layer.map.dest));
+        out.push_str(&format!(
+            "{:>6} {}\n",
+            "rows",
+            bar(&mut used.iter().map(|&idx| {
+                let row = &layer.map.rows[idx];
+                (row.source_start..row.source_end, row_label(idx))
+            }))
+        ));
+        out.push_str(&format!(
+            "{:>6} {}\n",
+            "in",
+            bar(&mut layer.fragments.iter().map(|f| (f.before.clone(), label(f))))
+        ));
+        out.push_str(&format!(
+            "{:>6} {}  {} fragment(s)\n",
+            "out",
+            bar(&mut layer.fragments.iter().map(|f| (f.after.clone(), label(f)))),
+            layer.fragments.len()
+        ));
+
+        for idx in used {
+            let row = &layer.map.rows[idx];
+            let moved = layer
+                .fragments
+                .iter()
+                .filter(|f| f.row == Some(idx))
+                .count();
+            out.push_str(&format!(
+                "       {} = {} {} {}, moves {:?} by {:+} ({} fragment(s))\n",
+                row_label(idx),
+                row.source_start + row.delta,
+                row.source_start,
+                row.source_end - row.source_start,
+                row.source_start..row.source_end,
+                row.delta,
+                moved
+            ));
+        }
+    }
+
+    out
+}
+
+/*
+  The same thing as a picture: one band per category with the fragments as
+  bars, coloured by seed range, and a band between each pair showing where
+  each fragment moved to. Rows that moved something are outlined above the
+  fragments they caught, with their letter.
+*/
+fn chart_svg(layers: &[Layer], seed_ranges: &[Range<i128>]) -> String {
+    const LEFT: f64 = 110.0;
+    const WIDTH: f64 = 900.0;
+    const BAR: f64 = 14.0;
+    const GAP: f64 = 70.0;
+    const TOP: f64 = 40.0;
+
+    let scale = Scale::new(layers, seed_ranges, WIDTH);
+    let x = |v: i128| LEFT + scale.at(v);
+    let level_y = |level: usize| TOP + level as f64 * (BAR + GAP);
+    let colour = |seed_range: usize| format!("hsl({}, 65%, 50%)", (seed_range * 137) % 360);
+    let height = level_y(layers.len()) + BAR + 30.0;
+
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" font-family=\"monospace\" font-size=\"11\">\n",
+        LEFT + WIDTH + 20.0,
+        height
+    );
+    out.push_str(&format!(
+        "  <text x=\"{}\" y=\"20\">{} .. {}</text>\n",
+        LEFT, scale.lo, scale.hi
+    ));
+
+    let mut bars = |level: usize,
+                    name: &str,
+                    ranges: &mut dyn Iterator<Item = (usize, Range<i128>)>| {
+        let y = level_y(level);
+        out.push_str(&format!(
+            "  <text x=\"5\" y=\"{}\">{}</text>\n",
+            y + BAR - 3.0,
+            name
+        ));
+        for (seed_range, range) in ranges {
+            out.push_str(&format!(
+                "  <rect x=\"{:.2}\" y=\"{}\" width=\"{:.2}\" height=\"{}\" fill=\"{}\"><title>{:?}</title></rect>\n",
+                x(range.start),
+                y,
+                (x(range.end) - x(range.start)).max(1.0),
+                BAR,
+                colour(seed_range),
+                range
+            ));
+        }
+    };
+
+    bars(0, "seed", &mut seed_ranges.iter().cloned().enumerate());
+    for (level, layer) in layers.iter().enumerate() {
+        bars(
+            level + 1,
+            &layer.map.dest,
+            &mut layer
+                .fragments
+                .iter()
+                .map(|f| (f.seed_range, f.after.clone())),
+        );
+    }
+
+    for (level, layer) in layers.iter().enumerate() {
+        let (top, bottom) = (level_y(level) + BAR, level_y(level + 1));
+
+        // Where every fragment went
+        for f in &layer.fragments {
+            out.push_str(&format!(
+                "  <polygon points=\"{:.2},{} {:.2},{} {:.2},{} {:.2},{}\" fill=\"{}\" fill-opacity=\"0.35\"/>\n",
+                x(f.before.start),
+                top,
+                x(f.before.end),
+                top,
+                x(f.after.end),
+                bottom,
+                x(f.after.start),
+                bottom,
+                colour(f.seed_range)
+            ));
+        }
+
+        // And the rows that did it, just under the fragments they caught
+        let mut used: Vec<usize> = layer.fragments.iter().filter_map(|f| f.row).collect();
+        used.sort_unstable();
+        used.dedup();
+        for idx in used {
+            let row = &layer.map.rows[idx];
+            let (start, end) = (x(row.source_start), x(row.source_end));
+            out.push_str(&format!(
+                "  <rect x=\"{:.2}\" y=\"{}\" width=\"{:.2}\" height=\"6\" fill=\"none\" stroke=\"black\"><title>{} {} {} ({:+})</title></rect>\n",
+                start,
+                top + 2.0,
+                (end - start).max(1.0),
+                row.source_start + row.delta,
+                row.source_start,
+                row.source_end - row.source_start,
+                row.delta
+            ));
+            out.push_str(&format!(
+                "  <text x=\"{:.2}\" y=\"{}\">{}</text>\n",
+                start,
+                top + 18.0,
+                row_label(idx)
+            ));
+        }
+    }
+
+    out.push_str("</svg>\n");
+    out
+}
+
+fn chart() {
+    let almanac = get_maps();
+    let maps = almanac
+        .route("seed", "location")
+        .unwrap_or_else(|e| panic!("{}", e));
+    let seed_ranges: Vec<Range<i128>> = get_seeds().chunks(2).map(|c| c[0]..c[0] + c[1]).collect();
+
+    let layers = fragment(&seed_ranges, &maps);
+    if utils::has_flag("--chart-svg") {
+        print!("{}", chart_svg(&layers, &seed_ranges));
+    } else {
+        print!("{}", chart_ascii(&layers, &seed_ranges));
+    }
+}
+
+fn seed_to_location(almanac: &Almanac) -> Piecewise {
+    let maps = almanac
+        .route("seed", "location")
+        .unwrap_or_else(|e| panic!("{}", e));
+
+    Piecewise::compose_all(&maps)
+}
+
+fn part1() {
+    let composed = seed_to_location(&get_maps());
+
+    // e.g. `cargo run -- 5 --composed` to see every layer squashed into one
+    if utils::has_flag("--composed") {
+        println!("{}", composed);
+    }
+
+    let locations = get_seeds().into_iter().map(|seed| composed.apply(seed));
+
+    done!(DAY, 1, locations.min().unwrap());
+}
+
+fn part2() {
+    let composed = seed_to_location(&get_maps());
+    let best = composed.min_over(&get_ranges()).unwrap();
+
+    // Work back from the best location to which seeds (and seed ranges) got there
+    let seeds = composed.preimage(&IntervalSet::from(best..best + 1));
+    for (idx, pair) in get_seeds().chunks(2).enumerate() {
+        let range = pair[0]..pair[0] + pair[1];
+        let ours = seeds.intersection(&IntervalSet::from(range.clone()));
+        if !ours.is_empty() {
+            println!(
+                "Location {} comes from seed(s) {} in seed range {} ({:?})",
+                best,
+                describe(&ours),
+                idx + 1,
+                range
+            );
+        }
+    }
+
+    done!(DAY, 2, best);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> (Vec<i128>, Piecewise, Almanac) {
+        let seeds = seeds_from(utils::get_test_reader_for_day(DAY));
+        let almanac = maps_from(utils::test_lines(DAY));
+        (seeds, seed_to_location(&almanac), almanac)
+    }
+
+    #[test]
+    fn example_answers() {
+        let (seeds, composed, _) = example();
+        assert_eq!(seeds.iter().map(|s| composed.apply(*s)).min(), Some(35));
+        assert_eq!(composed.min_over(&ranges_from(&seeds)), Some(46));
+    }
+
+    #[test]
+    fn composed_matches_each_layer_in_turn() {
+        let (_, composed, almanac) = example();
+        let layers: Vec<Piecewise> = almanac
+            .route("seed", "location")
+            .unwrap()
+            .into_iter()
+            .map(Piecewise::from_map)
+            .collect();
+
+        for x in -10..=120 {
+            let by_layer = layers.iter().fold(x, |x, layer| layer.apply(x));
+            assert_eq!(composed.apply(x), by_layer, "seed {}", x);
+        }
+    }
+
+    #[test]
+    fn min_over_matches_trying_every_seed() {
+        let (_, composed, _) = example();
+        for range in [0..1, 0..100, 13..27, 55..68, 79..93, 97..120, -5..3] {
+            let expected = range.clone().map(|x| composed.apply(x)).min();
+            assert_eq!(
+                composed.min_over(&IntervalSet::from(range.clone())),
+                expected,
+                "{:?}",
+                range
+            );
+        }
+        assert_eq!(composed.min_over(&IntervalSet::new()), None);
     }
 
-    ranges.sort_by(|a, b| a.start.cmp(&b.start));
+    #[test]
+    fn preimage_is_exactly_the_seeds_that_get_there() {
+        let (_, composed, _) = example();
+        // Every row is inside 0..100, so outside this everything maps to itself
+        let window = -10..=120;
+
+        for location in 0..=105 {
+            let seeds = composed.preimage(&IntervalSet::from(location..location + 1));
+            let expected: Vec<i128> = window
+                .clone()
+                .filter(|x| composed.apply(*x) == location)
+                .collect();
 
-    done!(DAY, 2, ranges.first().unwrap().start);
-}
\ No newline at end of file
+            let found: Vec<i128> = window.clone().filter(|x| seeds.contains(x)).collect();
+            assert_eq!(found, expected, "location {}", location);
+            // Nothing from outside the window either
+            assert_eq!(seeds.len(), expected.len() as i128, "location {}", location);
+        }
+    }
+}
@@ -5,6 +5,9 @@ pub fn main() {
     part2();
 }
 
+use crate::utils::math::isqrt_i128;
+use std::cmp::max;
+
 #[derive(Debug)]
 struct Race {
     max_time: i128,
@@ -30,17 +33,19 @@ impl Race {
          going to give us peak distance.
 
          To figure out the time to press to get a given distance, you flip it around to get:
-            time(dist) = (1/2) * (sqrt(race.max_time^2 - 4*dist) + race.max_time)
-        */
+            time(dist) = (1/2) * (race.max_time - sqrt(race.max_time^2 - 4*dist))
 
-        let c = self.max_time as f64;
-        let y = self.max_dist as f64;
-        let c2 = c.powi(2);
-
-        let sq = (c2 - (4.0 * y)).sqrt();
-        let p = c - sq;
-        let x = p / 2.0;
-        x.ceil() as i128
+         Floats lose precision long before i128 does, so use the integer square root to
+         land just under the real answer and walk up to the first time that reaches it.
+        */
+        let sq = isqrt_i128(self.max_time.pow(2) - 4 * self.max_dist)
+            .expect("This race can't be reached at all!");
+
+        let mut t = max((self.max_time - sq - 1) / 2, 0);
+        while self.distance(t) < self.max_dist {
+            t += 1;
+        }
+        t
     }
 }
 
@@ -100,7 +105,7 @@ fn sneaky_ways_to_win(r: &Race) -> u128 {
         min_to_win += 1;
     }
 
-    let mid = (r.max_time / 2) as i128;
+    let mid = r.max_time / 2;
 
     let width = mid - min_to_win;
     let mut max_to_win = mid + width;
@@ -118,7 +123,7 @@ fn part1() {
 
     let margin = races
         .iter()
-        .map(|r| sneaky_ways_to_win(r))
+        .map(sneaky_ways_to_win)
         .reduce(|acc, e| acc * e)
         .unwrap();
 
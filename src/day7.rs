@@ -5,8 +5,8 @@ pub fn main() {
     part2();
 }
 
+use crate::utils::Counter;
 use std::cmp::Ordering;
-use std::collections::HashMap;
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 enum HandKind {
@@ -50,69 +50,27 @@ impl Hand {
     }
 
     fn get_hand_kind(cards: &str, jokers: bool) -> HandKind {
-        /*
-         No native collections.Counter like in Python, so:
-           1. Generate the counts in a HashMap
-           2. Turn the HashMap into a Vec of tuples of (count, card)
-           3. Sort the Vec
-        */
-        let mut card_count: HashMap<char, u8> = HashMap::new();
-        for card in cards.chars() {
-            *card_count.entry(card).or_insert(0) += 1;
-        }
-
-        let mut count_by_card: Vec<(u8, &char)> = card_count.iter().map(|(k, v)| (*v, k)).collect();
+        let mut card_count: Counter<char> = cards.chars().collect();
 
-        let num_jokers = *card_count.get(&'J').unwrap_or(&0_u8);
+        // Jokers always do the most good pretending to be whatever we already have most of
+        let num_jokers = if jokers { card_count.remove(&'J') } else { 0 };
 
-        // Default sort is *ascending*, so largest at the end. Convenient!
-        count_by_card.sort();
-
-        if count_by_card.first().unwrap().0 == 5 {
-            return HandKind::Five;
+        let mut shape = card_count.shape();
+        if shape.is_empty() {
+            // Nothing but jokers
+            shape.push(0);
         }
-
-        let most_frequent = count_by_card.pop().unwrap();
-        let second_most_frequent = count_by_card.pop().unwrap();
-        let jokes_first = most_frequent.1 == &'J';
-        let jokes_second = second_most_frequent.1 == &'J';
-
-        if !jokers {
-            match most_frequent.0 {
-                4 => return HandKind::Four,
-                3 if second_most_frequent.0 == 2 => return HandKind::House,
-                3 => return HandKind::Three,
-                2 if second_most_frequent.0 == 2 => return HandKind::TwoPair,
-                2 => return HandKind::Pair,
-                1 => return HandKind::High,
-                0_u8 | 5_u8..=u8::MAX => panic!("Wrong number of cards entirely!"),
-            }
-        } else {
-            match most_frequent.0 {
-                4 if jokes_first => return HandKind::Five,
-                4 if num_jokers == 1 => return HandKind::Five,
-                4 => return HandKind::Four,
-
-                3 if jokes_first && second_most_frequent.0 == 2 => return HandKind::Five,
-                3 if num_jokers == 2 => return HandKind::Five,
-                3 if jokes_first => return HandKind::Four,
-                3 if num_jokers == 1 => return HandKind::Four,
-                3 if second_most_frequent.0 == 2 => return HandKind::House,
-                3 => return HandKind::Three,
-
-                2 if jokes_first && second_most_frequent.0 == 2 => return HandKind::Four,
-                2 if num_jokers == 2 && jokes_second => return HandKind::Four,
-                2 if second_most_frequent.0 == 2 && num_jokers == 1 => return HandKind::House,
-                2 if jokes_first && second_most_frequent.0 == 1 => return HandKind::Three,
-                2 if num_jokers == 1 => return HandKind::Three,
-                2 if second_most_frequent.0 == 2 => return HandKind::TwoPair,
-                2 => return HandKind::Pair,
-
-                1 if num_jokers == 1 => return HandKind::Pair,
-                1 => return HandKind::High,
-
-                0_u8 | 5_u8..=u8::MAX => panic!("Wrong number of cards entirely!"),
-            }
+        shape[0] += num_jokers;
+
+        match shape[..] {
+            [5] => HandKind::Five,
+            [4, ..] => HandKind::Four,
+            [3, 2] => HandKind::House,
+            [3, ..] => HandKind::Three,
+            [2, 2, ..] => HandKind::TwoPair,
+            [2, ..] => HandKind::Pair,
+            [1, ..] => HandKind::High,
+            _ => panic!("Wrong number of cards entirely!"),
         }
     }
 }
@@ -125,10 +83,16 @@ impl PartialEq for Hand {
 
 impl PartialOrd for Hand {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Hand {
+    fn cmp(&self, other: &Self) -> Ordering {
         let ord = self.kind.cmp(&other.kind);
 
         if ord != Ordering::Equal {
-            return Some(ord);
+            return ord;
         }
 
         let our_cards: Vec<char> = self.cards.chars().collect();
@@ -151,19 +115,13 @@ impl PartialOrd for Hand {
             }
 
             if our_card_strength > their_card_strength {
-                return Some(Ordering::Greater);
+                return Ordering::Greater;
             } else {
-                return Some(Ordering::Less);
+                return Ordering::Less;
             }
         }
 
-        Some(Ordering::Equal)
-    }
-}
-
-impl Ord for Hand {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap()
+        Ordering::Equal
     }
 }
 
@@ -186,7 +144,7 @@ const fn strength_of_card(c: &char, jokers: bool) -> Option<u32> {
 }
 
 fn part1() {
-    let mut hands: Vec<Hand> = crate::utils::lines(DAY).map(|l| Hand::new(l)).collect();
+    let mut hands: Vec<Hand> = crate::utils::lines(DAY).map(Hand::new).collect();
     hands.sort();
 
     let winnings: u32 = hands
@@ -200,7 +158,7 @@ fn part1() {
 
 fn part2() {
     let mut hands: Vec<Hand> = crate::utils::lines(DAY)
-        .map(|l| Hand::new_with_jokers(l))
+        .map(Hand::new_with_jokers)
         .collect();
     hands.sort();
 
@@ -5,6 +5,7 @@ pub fn main() {
     part2();
 }
 
+use crate::utils::{math::lcm_of, search::bfs};
 use std::collections::HashMap;
 
 type Label = String;
@@ -52,54 +53,25 @@ fn part1() {
     let (directions, graph) = get_directions_and_graph();
 
     let steps: Vec<char> = directions.chars().collect();
-    let mut step_idx = 0_usize;
-    let max_steps = steps.len();
-    let mut steps_taken = 0;
-
-    let mut curr_loc: Label = "AAA".to_string();
-    let mut current_node: &Node;
-
-    while curr_loc != "ZZZ" {
-        if step_idx == max_steps {
-            step_idx = 0;
-        }
-
-        current_node = graph.get(&curr_loc).unwrap();
-        if steps[step_idx] == 'L' {
-            curr_loc = current_node.left.clone();
-        } else {
-            curr_loc = current_node.right.clone();
-        }
 
-        steps_taken += 1;
-        step_idx += 1;
-    }
-
-    done!(DAY, 1, steps_taken);
-}
-
-/*
-  Taken from https://github.com/TheAlgorithms/Rust/blob/master/src/math/lcm_of_n_numbers.rs {{{
-*/
-fn lcm(nums: &[u128]) -> u128 {
-    if nums.len() == 1 {
-        return nums[0];
-    }
-
-    let a = nums[0];
-    let b = lcm(&nums[1..]);
-    a * b / gcd_of_two_numbers(a, b)
-}
-
-fn gcd_of_two_numbers(a: u128, b: u128) -> u128 {
-    if b == 0 {
-        return a;
-    }
-    gcd_of_two_numbers(b, a % b)
+    // Where we are on the map *and* where we are in the directions, since
+    // the same node can lead different places depending on the next step.
+    let walk = bfs(
+        ("AAA", 0_usize),
+        |(loc, step_idx)| {
+            let current_node = graph.get(*loc).unwrap();
+            let next_loc = if steps[*step_idx] == 'L' {
+                &current_node.left
+            } else {
+                &current_node.right
+            };
+            [(next_loc.as_str(), (step_idx + 1) % steps.len())]
+        },
+        |(loc, _)| *loc == "ZZZ",
+    );
+
+    done!(DAY, 1, walk.cost().expect("Never made it to ZZZ!"));
 }
-/*
-  }}}
-*/
 
 fn part2() {
     /*
@@ -129,7 +101,7 @@ fn part2() {
     let mut ghost_steps: Vec<u128> = Vec::new();
     let mut finished_ghost_count: u16;
 
-    while ghosts.is_empty() == false {
+    while !ghosts.is_empty() {
         if step_idx == max_steps {
             step_idx = 0;
         }
@@ -154,7 +126,7 @@ fn part2() {
             .fold(0, |acc, g| acc + (if g.ends_with("Z") { 1 } else { 0 }));
 
         if finished_ghost_count > 0 {
-            ghosts.retain(|g| g.ends_with("Z") == false);
+            ghosts.retain(|g| !g.ends_with("Z"));
 
             for _ in 0..finished_ghost_count {
                 ghost_steps.push(steps_taken);
@@ -162,5 +134,9 @@ fn part2() {
         }
     }
 
-    done!(DAY, 2, lcm(&ghost_steps));
+    done!(
+        DAY,
+        2,
+        lcm_of(&ghost_steps).expect("The ghosts never sync up in a u128!")
+    );
 }
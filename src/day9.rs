@@ -1,48 +1,76 @@
 const DAY: u8 = 9;
 
 pub fn main() {
-    part1();
-    part2();
-}
+    let histories: Vec<Vec<i128>> = crate::utils::lines(DAY).map(crate::utils::ints).collect();
 
-fn part1() {
-    let answer: i128 = crate::utils::lines(DAY)
-        .map(|l| get_last_num(crate::utils::ints(l)))
-        .sum();
+    /*
+      Every row of every history's difference pyramid, by (history, depth).
+      Both parts walk exactly the same pyramids, so part 2 gets every row
+      straight from the cache part 1 filled.
+    */
+    let mut rows = Memo::new(
+        |memo: &mut Memo<(usize, usize), Rc<[i128]>>, (history, depth): (usize, usize)| {
+            if depth == 0 {
+                Rc::from(histories[history].as_slice())
+            } else {
+                Rc::from(differences(&memo.get((history, depth - 1))))
+            }
+        },
+    );
 
-    done!(DAY, 1, answer);
+    // e.g. `cargo run -- 9 --stats` to see how much part 2 gets from the cache
+    let stats = crate::utils::has_flag("--stats");
+    part1(histories.len(), &mut rows);
+    if stats {
+        println!("Difference rows: {}", rows);
+    }
+    part2(histories.len(), &mut rows);
+    if stats {
+        println!("Difference rows: {}", rows);
+    }
 }
 
-fn get_last_num(nums: Vec<i128>) -> i128 {
-    if nums.iter().all(|n| *n == 0) {
-        return 0;
-    }
+use crate::utils::Memo;
+use std::rc::Rc;
+
+type Rows<'a> = Memo<'a, (usize, usize), Rc<[i128]>>;
 
-    return nums.last().unwrap()
-        + get_last_num(
-            (0..(nums.len() - 1))
-                .map(|i| nums[i + 1] - nums[i])
-                .collect(),
-        );
+fn differences(nums: &[i128]) -> Vec<i128> {
+    nums.windows(2).map(|w| w[1] - w[0]).collect()
 }
 
-fn part2() {
-    let answer: i128 = crate::utils::lines(DAY)
-        .map(|l| get_first_num(crate::utils::ints(l)))
+/// Rows of history's pyramid, top down, stopping before the first row of all zeros.
+fn pyramid(rows: &mut Rows, history: usize) -> Vec<Rc<[i128]>> {
+    (0..)
+        .map(|depth| rows.get((history, depth)))
+        .take_while(|row| !row.iter().all(|n| *n == 0))
+        .collect()
+}
+
+fn part1(num_histories: usize, rows: &mut Rows) {
+    // The next value is just the sum of the last value of every row
+    let answer: i128 = (0..num_histories)
+        .map(|h| {
+            pyramid(rows, h)
+                .iter()
+                .map(|row| row[row.len() - 1])
+                .sum::<i128>()
+        })
         .sum();
 
-    done!(DAY, 2, answer);
+    done!(DAY, 1, answer);
 }
 
-fn get_first_num(nums: Vec<i128>) -> i128 {
-    if nums.iter().all(|n| *n == 0) {
-        return 0;
-    }
+fn part2(num_histories: usize, rows: &mut Rows) {
+    // Working back up from the zeros, each first value is the row's first minus what's below it
+    let answer: i128 = (0..num_histories)
+        .map(|h| {
+            pyramid(rows, h)
+                .iter()
+                .rev()
+                .fold(0, |below, row| row[0] - below)
+        })
+        .sum();
 
-    return nums.first().unwrap()
-        - get_first_num(
-            (0..(nums.len() - 1))
-                .map(|i| nums[i + 1] - nums[i])
-                .collect(),
-        );
+    done!(DAY, 2, answer);
 }
@@ -19,16 +19,28 @@ pub mod day9;
 pub mod day10;
 
 fn main() {
-    vec![
-    day1::main,
-    day2::main,
-    day3::main,
-    day4::main,
-    day5::main,
-    day6::main,
-    day7::main,
-    day8::main,
-    day9::main,
-    day10::main,
-    ].last().unwrap()()
+    let days = [
+        day1::main,
+        day2::main,
+        day3::main,
+        day4::main,
+        day5::main,
+        day6::main,
+        day7::main,
+        day8::main,
+        day9::main,
+        day10::main,
+    ];
+
+    // Run the latest day, unless we're asked for a specific one, e.g. `cargo run -- 1`
+    match std::env::args()
+        .nth(1)
+        .and_then(|a| a.parse::<usize>().ok())
+    {
+        Some(day) => day
+            .checked_sub(1)
+            .and_then(|idx| days.get(idx))
+            .expect("We haven't done that day yet!")(),
+        None => days.last().unwrap()(),
+    }
 }
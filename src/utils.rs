@@ -1,5 +1,16 @@
+pub mod ansi;
+mod counter;
+mod intervals;
+pub mod math;
+mod memo;
+pub mod search;
+
+pub use counter::Counter;
+pub use intervals::IntervalSet;
+pub use memo::Memo;
+
 use std::{
-    env::current_dir,
+    env::{args, current_dir},
     fmt::Debug,
     fs::File,
     io::{BufRead, BufReader},
@@ -19,7 +30,7 @@ fn _get_reader(file_name: String) -> BufReader<File> {
     let current_dir = current_dir().expect("Can't get current directory?!");
     let in_f_path = current_dir.join("input").join(file_name);
     let file = File::open(in_f_path.to_str().unwrap())
-        .expect(format!("Really, the path ({:?}) is wrong?", in_f_path).as_str());
+        .unwrap_or_else(|_| panic!("Really, the path ({:?}) is wrong?", in_f_path));
 
     BufReader::new(file)
 }
@@ -47,4 +58,13 @@ where
     line.split_ascii_whitespace()
         .map(|c| c.parse::<T>().unwrap())
         .collect()
-}
\ No newline at end of file
+}
+/// Whether `name` (e.g. `--explain`) was given on the command line.
+pub fn has_flag(name: &str) -> bool {
+    args().any(|a| a == name)
+}
+
+/// The value following `name` on the command line, e.g. `fr` for `--lexicon fr`.
+pub fn arg_value(name: &str) -> Option<String> {
+    args().skip_while(|a| a != name).nth(1)
+}
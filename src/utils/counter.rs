@@ -0,0 +1,182 @@
+/*
+  A multiset, a la Python's collections.Counter.
+*/
+
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    ops::{Add, Sub},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Counter<T: Eq + Hash> {
+    counts: HashMap<T, usize>,
+}
+
+impl<T: Eq + Hash> Default for Counter<T> {
+    fn default() -> Self {
+        Self {
+            counts: HashMap::new(),
+        }
+    }
+}
+
+impl<T: Eq + Hash> Counter<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How many of `item` there are. Missing items are just zero.
+    pub fn get(&self, item: &T) -> usize {
+        *self.counts.get(item).unwrap_or(&0)
+    }
+
+    pub fn insert(&mut self, item: T) {
+        self.insert_n(item, 1);
+    }
+
+    pub fn insert_n(&mut self, item: T, n: usize) {
+        if n > 0 {
+            *self.counts.entry(item).or_insert(0) += n;
+        }
+    }
+
+    /// Takes every copy of `item` out, returning how many there were.
+    pub fn remove(&mut self, item: &T) -> usize {
+        self.counts.remove(item).unwrap_or(0)
+    }
+
+    /// Number of distinct items.
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// Number of items, counting every copy.
+    pub fn total(&self) -> usize {
+        self.counts.values().sum()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&T, usize)> {
+        self.counts.iter().map(|(k, v)| (k, *v))
+    }
+
+    /// The `n` most frequent items, most frequent first. Order among equal counts isn't fixed.
+    pub fn most_common(&self, n: usize) -> Vec<(&T, usize)> {
+        let mut by_count: Vec<(&T, usize)> = self.iter().collect();
+        by_count.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        by_count.truncate(n);
+        by_count
+    }
+
+    /// Just the counts, largest first, e.g. [3, 2] for a full house. Two
+    /// multisets with the same shape only differ by what's being counted.
+    pub fn shape(&self) -> Vec<usize> {
+        let mut counts: Vec<usize> = self.counts.values().copied().collect();
+        counts.sort_by(|a, b| b.cmp(a));
+        counts
+    }
+}
+
+impl<T: Eq + Hash> FromIterator<T> for Counter<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut counter = Self::new();
+        counter.extend(iter);
+        counter
+    }
+}
+
+impl<T: Eq + Hash> Extend<T> for Counter<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.insert(item);
+        }
+    }
+}
+
+impl<T: Eq + Hash> Add for Counter<T> {
+    type Output = Self;
+
+    fn add(mut self, other: Self) -> Self {
+        for (item, n) in other.counts {
+            self.insert_n(item, n);
+        }
+        self
+    }
+}
+
+impl<T: Eq + Hash> Sub for Counter<T> {
+    type Output = Self;
+
+    /// Counts bottom out at zero, and anything that hits zero is dropped.
+    fn sub(mut self, other: Self) -> Self {
+        for (item, n) in other.counts {
+            if let Some(count) = self.counts.get_mut(&item) {
+                if *count > n {
+                    *count -= n;
+                } else {
+                    self.counts.remove(&item);
+                }
+            }
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_and_totals() {
+        let counter: Counter<char> = "abracadabra".chars().collect();
+        assert_eq!(counter.get(&'a'), 5);
+        assert_eq!(counter.get(&'z'), 0);
+        assert_eq!(counter.len(), 5);
+        assert_eq!(counter.total(), 11);
+        assert_eq!(counter.shape(), vec![5, 2, 2, 1, 1]);
+        assert_eq!(counter.most_common(1), vec![(&'a', 5)]);
+    }
+
+    #[test]
+    fn insert_and_remove() {
+        let mut counter = Counter::new();
+        counter.insert_n("x", 0);
+        assert!(counter.is_empty());
+
+        counter.insert_n("x", 3);
+        counter.insert("x");
+        assert_eq!(counter.remove(&"x"), 4);
+        assert_eq!(counter.remove(&"x"), 0);
+        assert!(counter.is_empty());
+    }
+
+    #[test]
+    fn add() {
+        let a: Counter<u8> = [1, 1, 2].into_iter().collect();
+        let b: Counter<u8> = [1, 3].into_iter().collect();
+        let sum = a + b;
+        assert_eq!(sum.get(&1), 3);
+        assert_eq!(sum.get(&2), 1);
+        assert_eq!(sum.get(&3), 1);
+        assert_eq!(sum.total(), 5);
+    }
+
+    #[test]
+    fn sub_drops_keys_at_zero() {
+        let a: Counter<u8> = [1, 1, 1, 2, 2, 3].into_iter().collect();
+        let b: Counter<u8> = [1, 2, 2, 2, 4].into_iter().collect();
+        let diff = a - b;
+
+        assert_eq!(diff.get(&1), 2);
+        // 2 went below zero and 3 was untouched, 4 was never there
+        assert_eq!(diff.get(&2), 0);
+        assert_eq!(diff.get(&3), 1);
+        assert_eq!(diff.get(&4), 0);
+        assert_eq!(diff.len(), 2);
+        assert_eq!(diff.shape(), vec![2, 1]);
+    }
+}
@@ -0,0 +1,263 @@
+/*
+  A set of values stored as sorted, disjoint, half-open ranges.
+
+  Adjacent and overlapping ranges are always merged, so there's exactly one way
+  to store any given set. That keeps equality checks and min/len queries trivial.
+*/
+
+use std::{
+    cmp::{max, min},
+    ops::{Add, Range, Sub},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct IntervalSet<T> {
+    ranges: Vec<Range<T>>,
+}
+
+impl<T> IntervalSet<T>
+where
+    T: Copy + Ord + Default + Add<Output = T> + Sub<Output = T>,
+{
+    pub fn new() -> Self {
+        Self { ranges: Vec::new() }
+    }
+
+    /// Builds a set from any ranges at all; empty ones are dropped and the rest merged.
+    pub fn from_ranges<I: IntoIterator<Item = Range<T>>>(ranges: I) -> Self {
+        let mut ranges: Vec<Range<T>> = ranges.into_iter().filter(|r| r.start < r.end).collect();
+        ranges.sort_by_key(|r| r.start);
+
+        let mut merged: Vec<Range<T>> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                // Touching counts too, 0..5 and 5..10 is just 0..10
+                Some(last) if range.start <= last.end => last.end = max(last.end, range.end),
+                _ => merged.push(range),
+            }
+        }
+
+        Self { ranges: merged }
+    }
+
+    pub fn ranges(&self) -> &[Range<T>] {
+        &self.ranges
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Total number of values covered, across every range.
+    pub fn len(&self) -> T {
+        self.ranges
+            .iter()
+            .fold(T::default(), |acc, r| acc + (r.end - r.start))
+    }
+
+    pub fn min(&self) -> Option<T> {
+        self.ranges.first().map(|r| r.start)
+    }
+
+    pub fn contains(&self, val: &T) -> bool {
+        // Ranges are sorted and disjoint, so at most one can hold val
+        let idx = self.ranges.partition_point(|r| r.end <= *val);
+        self.ranges.get(idx).is_some_and(|r| r.start <= *val)
+    }
+
+    pub fn insert(&mut self, range: Range<T>) {
+        let ranges = std::mem::take(&mut self.ranges);
+        *self = Self::from_ranges(ranges.into_iter().chain(std::iter::once(range)));
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        Self::from_ranges(self.ranges.iter().chain(other.ranges.iter()).cloned())
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut out: Vec<Range<T>> = Vec::new();
+        let (mut i, mut j) = (0, 0);
+
+        while i < self.ranges.len() && j < other.ranges.len() {
+            let (a, b) = (&self.ranges[i], &other.ranges[j]);
+            let overlap_start = max(a.start, b.start);
+            let overlap_end = min(a.end, b.end);
+
+            if overlap_start < overlap_end {
+                out.push(overlap_start..overlap_end);
+            }
+
+            // Whichever finishes first can't overlap anything else on the other side
+            if a.end < b.end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+
+        Self { ranges: out }
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut out: Vec<Range<T>> = Vec::new();
+        let mut j = 0;
+
+        for range in &self.ranges {
+            let mut start = range.start;
+
+            // Skip anything in other that finished before we started
+            while j < other.ranges.len() && other.ranges[j].end <= start {
+                j += 1;
+            }
+
+            let mut k = j;
+            while k < other.ranges.len() && other.ranges[k].start < range.end {
+                let cut = &other.ranges[k];
+                if cut.start > start {
+                    out.push(start..cut.start);
+                }
+                start = max(start, cut.end);
+                k += 1;
+            }
+
+            if start < range.end {
+                out.push(start..range.end);
+            }
+        }
+
+        Self { ranges: out }
+    }
+
+    /// Everything in `bounds` that isn't in this set.
+    pub fn complement(&self, bounds: Range<T>) -> Self {
+        Self::from(bounds).difference(self)
+    }
+
+    /// Moves the part of this set that falls within `within` by `delta`, leaving
+    /// the rest where it is. Anything that lands on top of existing values merges.
+    pub fn shift(&self, within: Range<T>, delta: T) -> Self {
+        let window = Self::from(within);
+        let moved = self
+            .intersection(&window)
+            .ranges
+            .into_iter()
+            .map(|r| (r.start + delta)..(r.end + delta));
+
+        Self::from_ranges(self.difference(&window).ranges.into_iter().chain(moved))
+    }
+}
+
+impl<T> From<Range<T>> for IntervalSet<T>
+where
+    T: Copy + Ord + Default + Add<Output = T> + Sub<Output = T>,
+{
+    fn from(range: Range<T>) -> Self {
+        Self::from_ranges([range])
+    }
+}
+
+impl<T> FromIterator<Range<T>> for IntervalSet<T>
+where
+    T: Copy + Ord + Default + Add<Output = T> + Sub<Output = T>,
+{
+    fn from_iter<I: IntoIterator<Item = Range<T>>>(iter: I) -> Self {
+        Self::from_ranges(iter)
+    }
+}
+
+#[cfg(test)]
+// Expected sets are written out as lists of ranges, even when there's only one
+#[allow(clippy::single_range_in_vec_init)]
+mod tests {
+    use super::*;
+
+    fn set(ranges: &[Range<i64>]) -> IntervalSet<i64> {
+        IntervalSet::from_ranges(ranges.iter().cloned())
+    }
+
+    #[test]
+    fn touching_ranges_merge() {
+        assert_eq!(set(&[0..5, 5..10]).ranges(), &[0..10]);
+        assert_eq!(set(&[5..10, 0..5]).ranges(), &[0..10]);
+        assert_eq!(set(&[0..5, 3..4, 4..12]).ranges(), &[0..12]);
+        assert_eq!(set(&[0..5, 6..10]).ranges(), &[0..5, 6..10]);
+
+        let mut s = set(&[0..5]);
+        s.insert(5..8);
+        assert_eq!(s.ranges(), &[0..8]);
+    }
+
+    #[test]
+    fn empty_ranges_dropped() {
+        #[allow(clippy::reversed_empty_ranges)]
+        let s = set(&[3..3, 7..2, 1..2]);
+        assert_eq!(s.ranges(), &[1..2]);
+        assert!(set(&[4..4]).is_empty());
+        assert_eq!(set(&[4..4]), IntervalSet::new());
+    }
+
+    #[test]
+    fn intersection() {
+        let a = set(&[0..10, 20..30]);
+        let b = set(&[5..25, 28..40]);
+        assert_eq!(a.intersection(&b).ranges(), &[5..10, 20..25, 28..30]);
+        assert_eq!(b.intersection(&a), a.intersection(&b));
+        // Touching isn't overlapping
+        assert!(set(&[0..5]).intersection(&set(&[5..10])).is_empty());
+        assert!(a.intersection(&IntervalSet::new()).is_empty());
+    }
+
+    #[test]
+    fn difference() {
+        let a = set(&[0..10, 20..30]);
+        let b = set(&[5..25, 28..40]);
+        assert_eq!(a.difference(&b).ranges(), &[0..5, 25..28]);
+        assert_eq!(b.difference(&a).ranges(), &[10..20, 30..40]);
+        assert_eq!(
+            set(&[0..10]).difference(&set(&[2..3, 5..6])).ranges(),
+            &[0..2, 3..5, 6..10]
+        );
+        assert!(a.difference(&a).is_empty());
+    }
+
+    #[test]
+    fn complement() {
+        let a = set(&[2..4, 6..8]);
+        assert_eq!(a.complement(0..10).ranges(), &[0..2, 4..6, 8..10]);
+        assert_eq!(a.complement(3..7).ranges(), &[4..6]);
+        assert!(set(&[0..10]).complement(0..10).is_empty());
+    }
+
+    #[test]
+    fn shift_merges_onto_existing_values() {
+        // 10..15 moves down onto 3..8, overlapping 0..5
+        let a = set(&[0..5, 10..15]);
+        assert_eq!(a.shift(10..15, -7).ranges(), &[0..8]);
+
+        // Only the bit inside the window moves
+        let b = set(&[0..10]);
+        assert_eq!(b.shift(5..10, 10).ranges(), &[0..5, 15..20]);
+
+        // Landing right next to something joins up with it
+        let c = set(&[0..5, 20..25]);
+        assert_eq!(c.shift(20..25, -15).ranges(), &[0..10]);
+    }
+
+    #[test]
+    fn len_min_contains() {
+        let a = set(&[2..4, 10..15]);
+        assert_eq!(a.len(), 7);
+        assert_eq!(a.min(), Some(2));
+        assert!(a.contains(&2));
+        assert!(a.contains(&3));
+        assert!(!a.contains(&4));
+        assert!(!a.contains(&1));
+        assert!(a.contains(&14));
+        assert!(!a.contains(&15));
+
+        let empty: IntervalSet<i64> = IntervalSet::new();
+        assert_eq!(empty.len(), 0);
+        assert_eq!(empty.min(), None);
+        assert!(!empty.contains(&0));
+    }
+}
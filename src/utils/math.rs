@@ -0,0 +1,261 @@
+/*
+  Number theory helpers that keep turning up in puzzles: cycle lengths (lcm/crt),
+  quadratic bounds (isqrt) and modular arithmetic.
+
+  Everything here is iterative and overflow-checked. Anything that could overflow
+  returns an Option rather than silently wrapping.
+*/
+
+/// Greatest common divisor, via the iterative Euclidean algorithm.
+pub fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Least common multiple, or `None` if it doesn't fit in a `u128`.
+pub fn lcm(a: u128, b: u128) -> Option<u128> {
+    if a == 0 || b == 0 {
+        return Some(0);
+    }
+
+    // Divide first so we only overflow when the answer itself does
+    (a / gcd(a, b)).checked_mul(b)
+}
+
+/// Least common multiple of every number in `nums`. The LCM of nothing is 1.
+pub fn lcm_of(nums: &[u128]) -> Option<u128> {
+    nums.iter().try_fold(1_u128, |acc, n| lcm(acc, *n))
+}
+
+/// Extended Euclidean algorithm. Returns `(g, x, y)` such that `a*x + b*y == g`
+/// where `g` is the (non-negative) gcd of `a` and `b`.
+pub fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_x, mut x) = (1_i128, 0_i128);
+    let (mut old_y, mut y) = (0_i128, 1_i128);
+
+    while r != 0 {
+        let q = old_r / r;
+        (old_r, r) = (r, old_r - q * r);
+        (old_x, x) = (x, old_x - q * x);
+        (old_y, y) = (y, old_y - q * y);
+    }
+
+    if old_r < 0 {
+        (-old_r, -old_x, -old_y)
+    } else {
+        (old_r, old_x, old_y)
+    }
+}
+
+/// `(a * b) % m` without overflowing, even when `a * b` wouldn't fit in a `u128`.
+pub fn mul_mod(a: u128, b: u128, m: u128) -> u128 {
+    assert!(m != 0, "Modulus can't be zero!");
+    let (mut a, mut b) = (a % m, b % m);
+
+    if let Some(p) = a.checked_mul(b) {
+        return p % m;
+    }
+
+    // Too big to do directly, fall back to double-and-add
+    let mut result = 0_u128;
+    while b > 0 {
+        if b & 1 == 1 {
+            result = add_mod(result, a, m);
+        }
+        a = add_mod(a, a, m);
+        b >>= 1;
+    }
+    result
+}
+
+fn add_mod(a: u128, b: u128, m: u128) -> u128 {
+    // Both are already < m, so only one subtraction is ever needed
+    if a >= m - b {
+        a - (m - b)
+    } else {
+        a + b
+    }
+}
+
+/// `base^exp % m` by repeated squaring.
+pub fn mod_pow(base: u128, mut exp: u128, m: u128) -> u128 {
+    assert!(m != 0, "Modulus can't be zero!");
+    let mut result = 1 % m;
+    let mut base = base % m;
+
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exp >>= 1;
+    }
+    result
+}
+
+/// Modular inverse of `a` mod `m`, in `0..m`. `None` if `a` and `m` aren't coprime.
+pub fn mod_inverse(a: i128, m: i128) -> Option<i128> {
+    if m <= 0 {
+        return None;
+    }
+
+    let (g, x, _) = extended_gcd(a.rem_euclid(m), m);
+    if g != 1 {
+        return None;
+    }
+    Some(x.rem_euclid(m))
+}
+
+/// Chinese Remainder Theorem over `(residue, modulus)` pairs. The moduli don't
+/// need to be coprime.
+///
+/// Returns `(x, lcm)` where `x` is the smallest non-negative solution and every
+/// solution is `x + k*lcm`. `None` if the congruences disagree or the combined
+/// modulus overflows.
+pub fn crt(congruences: &[(i128, i128)]) -> Option<(i128, i128)> {
+    let mut x = 0_i128;
+    let mut m = 1_i128;
+
+    for &(a, n) in congruences {
+        if n <= 0 {
+            return None;
+        }
+        let a = a.rem_euclid(n);
+
+        // Solve x + m*k == a (mod n) for k
+        let (g, p, _) = extended_gcd(m, n);
+        let diff = (a - x).rem_euclid(n);
+        if diff % g != 0 {
+            return None;
+        }
+
+        let n_g = n / g;
+        let k = mul_mod((diff / g) as u128, p.rem_euclid(n_g) as u128, n_g as u128) as i128;
+
+        let new_m = m.checked_mul(n_g)?;
+        x = (x + mul_mod(m as u128, k as u128, new_m as u128) as i128).rem_euclid(new_m);
+        m = new_m;
+    }
+
+    Some((x, m))
+}
+
+/// Exact integer square root: the largest `r` with `r*r <= n`.
+pub fn isqrt_u128(n: u128) -> u128 {
+    if n < 2 {
+        return n;
+    }
+
+    // Newton's method from an over-estimate converges downwards onto the floor
+    let mut x = 1_u128 << ((128 - n.leading_zeros()).div_ceil(2));
+    loop {
+        let y = (x + n / x) / 2;
+        if y >= x {
+            return x;
+        }
+        x = y;
+    }
+}
+
+/// Exact integer square root for signed values. `None` for negative `n`.
+pub fn isqrt_i128(n: i128) -> Option<i128> {
+    if n < 0 {
+        return None;
+    }
+    Some(isqrt_u128(n as u128) as i128)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lcm_overflow_is_none() {
+        assert_eq!(lcm_of(&[4, 6, 10]), Some(60));
+        assert_eq!(lcm_of(&[]), Some(1));
+        assert_eq!(lcm(u128::MAX, u128::MAX - 1), None);
+        assert_eq!(lcm(0, 7), Some(0));
+    }
+
+    #[test]
+    fn crt_coprime() {
+        assert_eq!(crt(&[(2, 3), (3, 5), (2, 7)]), Some((23, 105)));
+        assert_eq!(crt(&[]), Some((0, 1)));
+        // Negative residues get normalised
+        assert_eq!(crt(&[(-1, 4), (0, 3)]), Some((3, 12)));
+    }
+
+    #[test]
+    fn crt_non_coprime() {
+        // 6 and 4 share a factor of 2, but agree on it
+        assert_eq!(crt(&[(4, 6), (2, 4)]), Some((10, 12)));
+        assert_eq!(crt(&[(3, 9), (3, 6)]), Some((3, 18)));
+    }
+
+    #[test]
+    fn crt_inconsistent() {
+        // Odd and even at the same time
+        assert_eq!(crt(&[(1, 6), (2, 4)]), None);
+        assert_eq!(crt(&[(1, 0)]), None);
+    }
+
+    #[test]
+    fn crt_overflowing_modulus() {
+        let big = i128::MAX / 2 + 1;
+        assert_eq!(crt(&[(0, big), (0, 3)]), None);
+    }
+
+    #[test]
+    fn mod_inverse_cases() {
+        assert_eq!(mod_inverse(3, 11), Some(4));
+        assert_eq!(mod_inverse(-3, 11), Some(7));
+        assert_eq!(mod_inverse(10, 17), Some(12));
+        assert_eq!(mod_inverse(4, 8), None);
+        assert_eq!(mod_inverse(1, 1), Some(0));
+        assert_eq!(mod_inverse(3, 0), None);
+        assert_eq!(mod_inverse(3, -7), None);
+    }
+
+    #[test]
+    fn isqrt_edges() {
+        assert_eq!(isqrt_u128(0), 0);
+        assert_eq!(isqrt_u128(1), 1);
+        assert_eq!(isqrt_u128(15), 3);
+        assert_eq!(isqrt_u128(16), 4);
+        assert_eq!(isqrt_u128(u128::MAX), u64::MAX as u128);
+        assert_eq!(isqrt_u128((u64::MAX as u128).pow(2)), u64::MAX as u128);
+        assert_eq!(
+            isqrt_u128((u64::MAX as u128).pow(2) - 1),
+            u64::MAX as u128 - 1
+        );
+        assert_eq!(isqrt_i128(-1), None);
+        assert_eq!(
+            isqrt_i128(i128::MAX),
+            Some(isqrt_u128(i128::MAX as u128) as i128)
+        );
+    }
+
+    #[test]
+    fn mul_mod_near_max() {
+        let m = u128::MAX;
+        // (m-1)^2 = m^2 - 2m + 1 == 1 (mod m)
+        assert_eq!(mul_mod(m - 1, m - 1, m), 1);
+        assert_eq!(mul_mod(m, 12345, m), 0);
+        // 2^127 * 2 = 2^128 == 1 (mod 2^128 - 1)
+        assert_eq!(mul_mod(1 << 127, 2, m), 1);
+        let p = u128::MAX - 158; // largest prime below 2^128
+        assert_eq!(mul_mod(p - 1, p - 1, p), 1);
+        assert_eq!(mul_mod(7, 8, 5), 1);
+    }
+
+    #[test]
+    fn mod_pow_fermat() {
+        let p = u128::MAX - 158;
+        assert_eq!(mod_pow(3, p - 1, p), 1);
+        assert_eq!(mod_pow(5, 0, 1), 0);
+        assert_eq!(mod_pow(2, 10, 1000), 24);
+    }
+}
@@ -0,0 +1,155 @@
+/*
+  Caches the results of a pure (usually recursive) function by its argument.
+
+  The function gets handed the Memo itself, so recursive calls go back through
+  the cache:
+
+      fn fib(memo: &mut Memo<u64, u64>, n: u64) -> u64 {
+          if n < 2 { return n; }
+          memo.get(n - 1) + memo.get(n - 2)
+      }
+
+      let mut fib_memo = Memo::new(fib);
+      fib_memo.get(90);
+
+  It can be a closure too, so whatever the function needs (a grid, the puzzle
+  input) can be borrowed rather than squeezed into every key:
+
+      let mut longest = Memo::new(|memo: &mut Memo<Point, usize>, p: Point| {
+          grid.downhill_from(p).map(|next| 1 + memo.get(next)).max().unwrap_or(0)
+      });
+*/
+
+use std::{collections::HashMap, fmt, hash::Hash, rc::Rc};
+
+type MemoFn<'a, K, V> = Rc<dyn Fn(&mut Memo<'a, K, V>, K) -> V + 'a>;
+
+pub struct Memo<'a, K, V> {
+    func: MemoFn<'a, K, V>,
+    cache: HashMap<K, V>,
+    hits: usize,
+    misses: usize,
+}
+
+impl<'a, K, V> Memo<'a, K, V>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+{
+    pub fn new(func: impl Fn(&mut Memo<'a, K, V>, K) -> V + 'a) -> Self {
+        Self {
+            func: Rc::new(func),
+            cache: HashMap::new(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    pub fn get(&mut self, key: K) -> V {
+        if let Some(val) = self.cache.get(&key) {
+            self.hits += 1;
+            return val.clone();
+        }
+
+        self.misses += 1;
+        // The function needs the whole memo for its own calls, so hold it separately
+        let func = Rc::clone(&self.func);
+        let val = func(self, key.clone());
+        self.cache.insert(key, val.clone());
+        val
+    }
+
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+
+    /// How many distinct arguments we're holding answers for.
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    /// Forget every cached answer and start the stats over, e.g. between part 1 and part 2.
+    pub fn clear(&mut self) {
+        self.cache.clear();
+        self.hits = 0;
+        self.misses = 0;
+    }
+}
+
+impl<K, V> fmt::Display for Memo<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} hits, {} misses, {} cached",
+            self.hits,
+            self.misses,
+            self.cache.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fib(memo: &mut Memo<u64, u64>, n: u64) -> u64 {
+        if n < 2 {
+            return n;
+        }
+        memo.get(n - 1) + memo.get(n - 2)
+    }
+
+    #[test]
+    fn recursive_hits_and_misses() {
+        let mut memo = Memo::new(fib);
+        assert_eq!(memo.get(90), 2880067194370816120);
+        // Each of 0..=90 is worked out once, then every n from 3 up finds n-2 already cached
+        assert_eq!(memo.misses(), 91);
+        assert_eq!(memo.hits(), 88);
+        assert_eq!(memo.len(), 91);
+
+        assert_eq!(memo.get(90), 2880067194370816120);
+        assert_eq!((memo.hits(), memo.misses()), (89, 91));
+        assert_eq!(memo.to_string(), "89 hits, 91 misses, 91 cached");
+    }
+
+    #[test]
+    fn clear_resets_cache_and_stats() {
+        let mut memo = Memo::new(fib);
+        memo.get(10);
+        memo.clear();
+        assert!(memo.is_empty());
+        assert_eq!((memo.hits(), memo.misses()), (0, 0));
+
+        // Everything has to be worked out again
+        assert_eq!(memo.get(10), 55);
+        assert_eq!(memo.misses(), 11);
+    }
+
+    #[test]
+    fn capturing_closure() {
+        let weights = [3, 1, 4, 1, 5];
+        let calls = std::cell::Cell::new(0);
+        let mut suffix_sum = Memo::new(|memo: &mut Memo<usize, u32>, i: usize| {
+            calls.set(calls.get() + 1);
+            if i == weights.len() {
+                0
+            } else {
+                weights[i] + memo.get(i + 1)
+            }
+        });
+
+        assert_eq!(suffix_sum.get(0), 14);
+        assert_eq!(suffix_sum.get(2), 10);
+        assert_eq!(calls.get(), 6);
+        assert_eq!((suffix_sum.hits(), suffix_sum.misses()), (1, 6));
+    }
+}
@@ -0,0 +1,269 @@
+/*
+  Graph searches over implicit graphs. Rather than building a graph up front,
+  callers hand over a closure that lists the neighbours of a given state, so
+  anything hashable can be a node: grid points, (position, direction) pairs,
+  whole puzzle states, etc.
+
+  All of them stop as soon as is_goal() says so. Pass |_| false to explore
+  everything reachable instead.
+*/
+
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, VecDeque},
+    hash::Hash,
+    ops::Add,
+};
+
+#[derive(Debug)]
+pub struct SearchResult<S, C> {
+    /// Cost of the cheapest known way to reach each visited state.
+    pub dist: HashMap<S, C>,
+    /// The state we came from to get that cheapest cost. The start has no entry.
+    pub prev: HashMap<S, S>,
+    /// The first goal state reached, if any.
+    pub goal: Option<S>,
+}
+
+impl<S, C> SearchResult<S, C>
+where
+    S: Clone + Eq + Hash,
+{
+    /// Walks the predecessor map back from `target`, returning start..=target.
+    pub fn path_to(&self, target: &S) -> Option<Vec<S>> {
+        if !self.dist.contains_key(target) {
+            return None;
+        }
+
+        let mut path = vec![target.clone()];
+        let mut curr = target;
+        while let Some(p) = self.prev.get(curr) {
+            path.push(p.clone());
+            curr = p;
+        }
+
+        path.reverse();
+        Some(path)
+    }
+
+    /// The path to the goal that stopped the search, if we found one.
+    pub fn path(&self) -> Option<Vec<S>> {
+        self.goal.as_ref().and_then(|g| self.path_to(g))
+    }
+
+    pub fn cost(&self) -> Option<&C> {
+        self.goal.as_ref().and_then(|g| self.dist.get(g))
+    }
+}
+
+/// Breadth-first search where every step costs 1.
+pub fn bfs<S, N, I, G>(start: S, mut neighbours: N, mut is_goal: G) -> SearchResult<S, usize>
+where
+    S: Clone + Eq + Hash,
+    N: FnMut(&S) -> I,
+    I: IntoIterator<Item = S>,
+    G: FnMut(&S) -> bool,
+{
+    let mut result = SearchResult {
+        dist: HashMap::from([(start.clone(), 0)]),
+        prev: HashMap::new(),
+        goal: None,
+    };
+    let mut queue = VecDeque::from([start]);
+
+    while let Some(curr) = queue.pop_front() {
+        if is_goal(&curr) {
+            result.goal = Some(curr);
+            break;
+        }
+
+        let next_dist = result.dist[&curr] + 1;
+        for next in neighbours(&curr) {
+            if result.dist.contains_key(&next) {
+                continue;
+            }
+
+            result.dist.insert(next.clone(), next_dist);
+            result.prev.insert(next.clone(), curr.clone());
+            queue.push_back(next);
+        }
+    }
+
+    result
+}
+
+/// Dijkstra's algorithm. `neighbours` yields (state, cost to step there) pairs
+/// and costs must never be negative.
+pub fn dijkstra<S, C, N, I, G>(start: S, neighbours: N, is_goal: G) -> SearchResult<S, C>
+where
+    S: Clone + Eq + Hash,
+    C: Copy + Ord + Default + Add<Output = C>,
+    N: FnMut(&S) -> I,
+    I: IntoIterator<Item = (S, C)>,
+    G: FnMut(&S) -> bool,
+{
+    astar(start, neighbours, |_| C::default(), is_goal)
+}
+
+/// A* search. `heuristic` must never overestimate the remaining cost to a goal,
+/// otherwise the path found may not be the cheapest.
+pub fn astar<S, C, N, I, H, G>(
+    start: S,
+    mut neighbours: N,
+    mut heuristic: H,
+    mut is_goal: G,
+) -> SearchResult<S, C>
+where
+    S: Clone + Eq + Hash,
+    C: Copy + Ord + Default + Add<Output = C>,
+    N: FnMut(&S) -> I,
+    I: IntoIterator<Item = (S, C)>,
+    H: FnMut(&S) -> C,
+    G: FnMut(&S) -> bool,
+{
+    let mut result = SearchResult {
+        dist: HashMap::from([(start.clone(), C::default())]),
+        prev: HashMap::new(),
+        goal: None,
+    };
+
+    // States don't need to be Ord, so the heap holds indexes into seen instead
+    let mut seen: Vec<(S, C)> = vec![(start.clone(), C::default())];
+    let mut heap = BinaryHeap::from([Reverse((heuristic(&start), 0_usize))]);
+
+    while let Some(Reverse((_, idx))) = heap.pop() {
+        let (curr, cost) = seen[idx].clone();
+
+        if result.dist[&curr] < cost {
+            // Stale entry, we've already found a cheaper way here
+            continue;
+        }
+
+        if is_goal(&curr) {
+            result.goal = Some(curr);
+            break;
+        }
+
+        for (next, step) in neighbours(&curr) {
+            let next_cost = cost + step;
+            if result.dist.get(&next).is_some_and(|d| *d <= next_cost) {
+                continue;
+            }
+
+            result.dist.insert(next.clone(), next_cost);
+            result.prev.insert(next.clone(), curr.clone());
+            heap.push(Reverse((next_cost + heuristic(&next), seen.len())));
+            seen.push((next, next_cost));
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a -1- b -1- c, plus a direct a -10- c and a dead end d hanging off c
+    fn weighted(node: &char) -> Vec<(char, u32)> {
+        match node {
+            'a' => vec![('c', 10), ('b', 1)],
+            'b' => vec![('a', 1), ('c', 1)],
+            'c' => vec![('a', 10), ('b', 1), ('d', 5)],
+            _ => vec![],
+        }
+    }
+
+    #[test]
+    fn bfs_distances_and_paths() {
+        // 0..10 along a line, with a shortcut from 2 to 7
+        let result = bfs(
+            0,
+            |n: &i32| {
+                let mut next = vec![n - 1, n + 1];
+                if *n == 2 {
+                    next.push(7);
+                }
+                next.into_iter().filter(|m| (0..10).contains(m))
+            },
+            |_| false,
+        );
+
+        assert_eq!(result.goal, None);
+        assert_eq!(result.dist[&9], 5);
+        assert_eq!(result.path_to(&9), Some(vec![0, 1, 2, 7, 8, 9]));
+        assert_eq!(result.path_to(&0), Some(vec![0]));
+        assert_eq!(result.path_to(&42), None);
+        assert_eq!(result.path(), None);
+    }
+
+    #[test]
+    fn bfs_stops_at_goal() {
+        let result = bfs(0, |n: &u32| [n + 1, n + 2], |n| *n == 7);
+        assert_eq!(result.goal, Some(7));
+        assert_eq!(result.cost(), Some(&4));
+        assert_eq!(result.path().unwrap().len(), 5);
+    }
+
+    #[test]
+    fn dijkstra_skips_stale_entries() {
+        let mut expanded = Vec::new();
+        let result = dijkstra(
+            'a',
+            |n: &char| {
+                expanded.push(*n);
+                weighted(n)
+            },
+            |_| false,
+        );
+
+        // c first goes on the heap at 10, then again at 2 via b. The 10 is
+        // popped later and has to be ignored rather than expanded again.
+        assert_eq!(expanded.iter().filter(|n| **n == 'c').count(), 1);
+        assert_eq!(expanded.len(), 4);
+        assert_eq!(result.dist[&'c'], 2);
+        assert_eq!(result.dist[&'d'], 7);
+        assert_eq!(result.path_to(&'d'), Some(vec!['a', 'b', 'c', 'd']));
+    }
+
+    #[test]
+    fn dijkstra_goal_and_unreachable() {
+        let result = dijkstra('a', weighted, |n| *n == 'c');
+        assert_eq!(result.cost(), Some(&2));
+        assert_eq!(result.path(), Some(vec!['a', 'b', 'c']));
+
+        let result = dijkstra('d', weighted, |n| *n == 'a');
+        assert_eq!(result.goal, None);
+        assert_eq!(result.cost(), None);
+        assert_eq!(result.path_to(&'d'), Some(vec!['d']));
+    }
+
+    #[test]
+    fn astar_matches_dijkstra() {
+        // 6x6 grid with a cost per cell, moving in 4 directions
+        let weight = |(x, y): (i32, i32)| ((x * 7 + y * 3) % 5 + 1) as u32;
+        let neighbours = |&(x, y): &(i32, i32)| {
+            [(1, 0), (-1, 0), (0, 1), (0, -1)]
+                .into_iter()
+                .map(move |(dx, dy)| (x + dx, y + dy))
+                .filter(|&(x, y)| (0..6).contains(&x) && (0..6).contains(&y))
+                .map(move |p| (p, weight(p)))
+        };
+        let goal = (5, 5);
+
+        let slow = dijkstra((0, 0), neighbours, |p| *p == goal);
+        let fast = astar(
+            (0, 0),
+            neighbours,
+            |&(x, y)| ((goal.0 - x) + (goal.1 - y)) as u32,
+            |p| *p == goal,
+        );
+
+        assert_eq!(slow.cost(), fast.cost());
+        let path = fast.path().unwrap();
+        assert_eq!(path.first(), Some(&(0, 0)));
+        assert_eq!(path.last(), Some(&goal));
+        let walked: u32 = path.iter().skip(1).map(|p| weight(*p)).sum();
+        assert_eq!(Some(&walked), fast.cost());
+    }
+}